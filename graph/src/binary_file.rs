@@ -0,0 +1,273 @@
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+use crate::storage::Array;
+use crate::{atomic_write, checksum, Graph, GraphError};
+
+/// The first bytes of every binary graph file.
+const MAGIC: &[u8; 8] = b"WCGRAPH\0";
+
+/// The version of the binary graph format. Change it if the format changes,
+/// so that old files are rejected rather than misread.
+pub const BINARY_FORMAT_VERSION: u32 = 1;
+
+/// The size of the header: the magic bytes, the version, the word length, the
+/// number of vertices and of adjacencies, and the checksum.
+const HEADER_SIZE: usize = 32;
+
+impl Graph {
+    /// Writes the graph in a binary format which `load_from_binary_file` can
+    /// read far faster than an adjacency file. After the header come arrays of
+    /// little-endian `u32`s:
+    ///
+    /// * the adjacency lists in compressed sparse row form, as in `CsrGraph`:
+    ///   the offset of each vertex's list plus a final offset, then the lists
+    /// * the component of each vertex
+    /// * the indexes of the vertices in order of their words
+    ///
+    /// and then the words themselves, which all have the same length and so
+    /// need no separators. This is exactly how `Graph` stores them, and the
+    /// header is a multiple of 4 bytes long, so that every array is aligned
+    /// and a loaded graph can use them where they are. The checksum covers
+    /// everything after the header. The file is written with `atomic_write`.
+    pub fn write_binary_file<P: AsRef<Path>>(&self, filename: P) -> io::Result<()> {
+        let filename = filename.as_ref();
+        let to_u32 = |n: usize| u32::try_from(n).expect("Graph is too large for 32-bit indices");
+
+        let mut body = Vec::new();
+        for array in [&self.offsets, &self.neighbours, &self.components, &self.words_in_order] {
+            array.iter().for_each(|n| body.extend_from_slice(&n.to_le_bytes()));
+        }
+        body.extend_from_slice(&self.words);
+
+        let mut contents = Vec::with_capacity(HEADER_SIZE + body.len());
+        contents.extend_from_slice(MAGIC);
+        let num_adjacencies = self.neighbours.len();
+        for n in [BINARY_FORMAT_VERSION, to_u32(self.word_length()), to_u32(self.size()), to_u32(num_adjacencies)] {
+            contents.extend_from_slice(&n.to_le_bytes());
+        }
+        contents.extend_from_slice(&checksum(&body).to_le_bytes());
+        contents.extend_from_slice(&body);
+
+        atomic_write(filename, &contents)
+    }
+
+    /// Reads a graph written by `write_binary_file`. The file is memory-mapped
+    /// and, once it has been checked, the graph borrows its words and arrays
+    /// straight from the mapping, so nothing needs to be parsed, copied or
+    /// recalculated.
+    pub fn load_from_binary_file<P: AsRef<Path>>(filename: P) -> Result<Self, GraphError> {
+        let filename = filename.as_ref();
+        let malformed = |reason: &str| GraphError::Malformed {
+            filename: filename.to_path_buf(),
+            reason: reason.to_string(),
+        };
+        let io_error = |error| GraphError::Io {
+            filename: filename.to_path_buf(),
+            error,
+        };
+
+        let file = File::open(filename).map_err(io_error)?;
+        // Safety: the file is only ever read, and pre_calc writes a new file and
+        // renames it into place rather than changing an existing one.
+        let map = Arc::new(unsafe { Mmap::map(&file) }.map_err(io_error)?);
+        let bytes: &[u8] = &map;
+
+        if bytes.len() < HEADER_SIZE || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(malformed("it is not a binary graph file"));
+        }
+
+        let version = read_u32(bytes, 8);
+        if version != BINARY_FORMAT_VERSION {
+            return Err(GraphError::WrongVersion {
+                filename: filename.to_path_buf(),
+                version,
+                expected: BINARY_FORMAT_VERSION,
+            });
+        }
+
+        let word_length = read_u32(bytes, 12) as usize;
+        let size = read_u32(bytes, 16) as usize;
+        let num_adjacencies = read_u32(bytes, 20) as usize;
+        let expected_checksum = u64::from_le_bytes(bytes[24..HEADER_SIZE].try_into().unwrap());
+
+        let body = &bytes[HEADER_SIZE..];
+        if body.len() != 4 * (3 * size + 1 + num_adjacencies) + size * word_length {
+            return Err(malformed("its length does not match its header, the file may be incomplete"));
+        }
+
+        if checksum(body) != expected_checksum {
+            return Err(GraphError::WrongChecksum {
+                filename: filename.to_path_buf(),
+            });
+        }
+
+        if size == 0 {
+            return Err(GraphError::Empty {
+                filename: filename.to_path_buf(),
+            });
+        }
+
+        // The start of each array in the file, and its length.
+        let offsets_at = (HEADER_SIZE, size + 1);
+        let neighbours_at = (offsets_at.0 + 4 * offsets_at.1, num_adjacencies);
+        let components_at = (neighbours_at.0 + 4 * neighbours_at.1, size);
+        let words_in_order_at = (components_at.0 + 4 * components_at.1, size);
+        let words_at = (words_in_order_at.0 + 4 * words_in_order_at.1, size * word_length);
+
+        let u32s = |(start, len): (usize, usize)| read_u32s(&bytes[start..start + 4 * len]);
+        let words = &bytes[words_at.0..];
+
+        let offsets: Vec<usize> = u32s(offsets_at).collect();
+        if offsets[0] != 0 || offsets[size] != num_adjacencies || offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err(malformed("the adjacency list offsets are out of order"));
+        }
+
+        if u32s(neighbours_at).chain(u32s(words_in_order_at)).any(|idx| idx >= size) {
+            return Err(malformed("a vertex index is out of range"));
+        }
+
+        let word = |idx: usize| &words[idx * word_length..(idx + 1) * word_length];
+        if (0..size).any(|idx| std::str::from_utf8(word(idx)).is_err()) {
+            return Err(malformed("a word is not valid UTF-8"));
+        }
+
+        // `find` relies on this being sorted, and as each word can only come
+        // after the one before it, it also means no vertex is listed twice.
+        let words_in_order: Vec<usize> = u32s(words_in_order_at).collect();
+        if words_in_order.windows(2).any(|w| word(w[0]) >= word(w[1])) {
+            return Err(malformed("the words are not in order"));
+        }
+
+        // The file is little-endian, so on any other machine the arrays have
+        // to be converted rather than borrowed.
+        let u32_array = |at: (usize, usize)| {
+            if cfg!(target_endian = "little") {
+                Array::mapped(&map, at.0, at.1)
+            } else {
+                Array::Owned(u32s(at).map(|n| n as u32).collect())
+            }
+        };
+
+        Ok(Graph {
+            word_length,
+            words: Array::mapped(&map, words_at.0, words_at.1),
+            offsets: u32_array(offsets_at),
+            neighbours: u32_array(neighbours_at),
+            components: u32_array(components_at),
+            words_in_order: u32_array(words_in_order_at),
+        })
+    }
+}
+
+/// Reads the little-endian `u32` at `offset` in `bytes`.
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Iterates over an array of little-endian `u32`s as indexes.
+fn read_u32s(bytes: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn test_graph() -> Graph {
+        Graph::from_words(["cold", "cord", "card", "ward", "warm", "tree", "free", "flee"]).unwrap()
+    }
+
+    /// Writes the test graph to a file of its own in the temporary directory,
+    /// and returns the file's name and contents.
+    fn write_test_file(name: &str) -> (PathBuf, Vec<u8>) {
+        let filename = std::env::temp_dir().join(format!("binary_file_{}_{}.bin", std::process::id(), name));
+        test_graph().write_binary_file(&filename).unwrap();
+        let bytes = fs::read(&filename).unwrap();
+        (filename, bytes)
+    }
+
+    /// Rewrites the body of a file and its checksum to match.
+    fn rewrite_body(filename: &Path, mut bytes: Vec<u8>, change: impl FnOnce(&mut [u8])) {
+        change(&mut bytes[HEADER_SIZE..]);
+        let new_checksum = checksum(&bytes[HEADER_SIZE..]);
+        bytes[24..HEADER_SIZE].copy_from_slice(&new_checksum.to_le_bytes());
+        fs::write(filename, bytes).unwrap();
+    }
+
+    #[test]
+    fn graph_survives_a_round_trip() {
+        let graph = test_graph();
+        let (filename, _) = write_test_file("round_trip");
+        let loaded = Graph::load_from_binary_file(&filename).unwrap();
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(loaded.size(), graph.size());
+        assert_eq!(loaded.word_length(), graph.word_length());
+        assert_eq!(*loaded.words, *graph.words);
+        assert_eq!(*loaded.offsets, *graph.offsets);
+        assert_eq!(*loaded.neighbours, *graph.neighbours);
+        assert_eq!(*loaded.components, *graph.components);
+        assert_eq!(*loaded.words_in_order, *graph.words_in_order);
+        assert_eq!(loaded.find("ward"), Some(3));
+        assert_eq!(loaded.neighbour_words("ward").unwrap().collect::<Vec<_>>(), ["card", "warm"]);
+
+        // The loaded graph borrows its arrays rather than copying them.
+        assert!(matches!(loaded.words, Array::Mapped { .. }));
+        if cfg!(target_endian = "little") {
+            assert!(matches!(loaded.neighbours, Array::Mapped { .. }));
+        }
+    }
+
+    #[test]
+    fn damaged_file_is_rejected() {
+        let (filename, mut bytes) = write_test_file("damaged");
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(&filename, bytes).unwrap();
+
+        let result = Graph::load_from_binary_file(&filename);
+        fs::remove_file(&filename).unwrap();
+        assert!(matches!(result, Err(GraphError::WrongChecksum { .. })));
+    }
+
+    #[test]
+    fn other_version_is_rejected() {
+        let (filename, mut bytes) = write_test_file("version");
+        bytes[8..12].copy_from_slice(&(BINARY_FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&filename, bytes).unwrap();
+
+        let result = Graph::load_from_binary_file(&filename);
+        fs::remove_file(&filename).unwrap();
+        assert!(matches!(
+            result,
+            Err(GraphError::WrongVersion { version, expected: BINARY_FORMAT_VERSION, .. })
+                if version == BINARY_FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn words_out_of_order_are_rejected() {
+        let graph = test_graph();
+        let (filename, bytes) = write_test_file("order");
+
+        // Swap the first two entries of `words_in_order`.
+        let start = 4 * (2 * graph.size() + 1 + 2 * graph.edges().count());
+        rewrite_body(&filename, bytes, |body| {
+            let (first, second) = body[start..start + 8].split_at_mut(4);
+            first.swap_with_slice(second);
+        });
+
+        let result = Graph::load_from_binary_file(&filename);
+        fs::remove_file(&filename).unwrap();
+        assert!(matches!(result, Err(GraphError::Malformed { reason, .. }) if reason == "the words are not in order"));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::Graph;
+
+/// The biconnected components ("blocks") of a graph together with the cut
+/// vertices (articulation points) that join them. Viewed as a bipartite graph
+/// of blocks and cut vertices this forms a tree for each component of the
+/// graph, called the block-cut tree.
+///
+/// A simple path that leaves a block through a cut vertex can never re-enter
+/// it, so the longest path problem can be solved one block at a time.
+#[derive(Debug)]
+pub struct BlockCutTree {
+    /// The vertices in each block, as indexes into `Graph::vertices`. An
+    /// isolated vertex forms a block on its own.
+    pub blocks: Vec<Vec<usize>>,
+    /// For each vertex in the graph, the blocks that it belongs to. Cut
+    /// vertices belong to more than one block, all other vertices to exactly one.
+    pub vertex_blocks: Vec<Vec<usize>>,
+}
+
+impl BlockCutTree {
+    /// Returns true if removing the vertex would disconnect its component.
+    pub fn is_cut_vertex(&self, vertex_index: usize) -> bool {
+        self.vertex_blocks[vertex_index].len() > 1
+    }
+
+    /// Returns the cut vertices of a block, i.e. the points at which a path
+    /// can leave the block for another one.
+    pub fn cut_vertices(&self, block: usize) -> impl Iterator<Item = usize> + '_ {
+        self.blocks[block]
+            .iter()
+            .copied()
+            .filter(move |&v| self.is_cut_vertex(v))
+    }
+}
+
+impl Graph {
+    /// Calculates the biconnected components of the graph and the cut
+    /// vertices joining them. This uses the Hopcroft-Tarjan algorithm, run
+    /// iteratively because a component can have thousands of vertices.
+    pub fn block_cut_tree(&self) -> BlockCutTree {
+        const UNVISITED: usize = usize::MAX;
+
+        let n = self.size();
+        let mut discovery = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut next_discovery = 0;
+
+        let mut blocks = Vec::new();
+        let mut vertex_stack = Vec::new();
+        // Each entry is a vertex and the position in its adjacency
+        // list of the next neighbour to examine.
+        let mut dfs_stack: Vec<(usize, usize)> = Vec::new();
+
+        for root in 0..n {
+            if discovery[root] != UNVISITED {
+                continue;
+            }
+
+            discovery[root] = next_discovery;
+            low[root] = next_discovery;
+            next_discovery += 1;
+
+            if self.degree(root) == 0 {
+                blocks.push(vec![root]);
+                continue;
+            }
+
+            vertex_stack.push(root);
+            dfs_stack.push((root, 0));
+
+            while let Some(&(v, cursor)) = dfs_stack.last() {
+                let adjacency_list = self.adjacency_list(v);
+
+                if cursor < adjacency_list.len() {
+                    dfs_stack.last_mut().unwrap().1 += 1;
+                    let w = adjacency_list[cursor] as usize;
+
+                    if discovery[w] == UNVISITED {
+                        discovery[w] = next_discovery;
+                        low[w] = next_discovery;
+                        next_discovery += 1;
+                        vertex_stack.push(w);
+                        dfs_stack.push((w, 0));
+                    } else {
+                        // A back edge (or the edge to our parent, which
+                        // cannot lower `low` below the parent's discovery
+                        // time and so does no harm).
+                        low[v] = low[v].min(discovery[w]);
+                    }
+
+                    continue;
+                }
+
+                dfs_stack.pop();
+                if let Some(&(parent, _)) = dfs_stack.last() {
+                    low[parent] = low[parent].min(low[v]);
+
+                    if low[v] >= discovery[parent] {
+                        // The parent separates v's subtree from the rest of
+                        // the graph (or is the root), so the subtree plus the
+                        // parent forms a block.
+                        let mut block = Vec::new();
+                        while let Some(u) = vertex_stack.pop() {
+                            block.push(u);
+                            if u == v {
+                                break;
+                            }
+                        }
+                        block.push(parent);
+                        blocks.push(block);
+                    }
+                }
+            }
+
+            vertex_stack.clear();
+        }
+
+        let mut vertex_blocks = vec![Vec::new(); n];
+        for (block_number, block) in blocks.iter().enumerate() {
+            for &v in block {
+                vertex_blocks[v].push(block_number);
+            }
+        }
+
+        BlockCutTree {
+            blocks,
+            vertex_blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Graph, GraphBuilder};
+
+    /// A triangle joined by a bridge to a square, with a leaf hanging off
+    /// the square and an isolated vertex.
+    fn test_graph() -> Graph {
+        let mut builder = GraphBuilder::new();
+        let edges = [
+            ("a", "b"), ("b", "c"), ("c", "a"),
+            ("c", "d"),
+            ("d", "e"), ("e", "f"), ("f", "g"), ("g", "d"),
+            ("g", "h"),
+        ];
+        for (word1, word2) in edges {
+            builder.add_edge(word1, word2).unwrap();
+        }
+        builder.add_word("i").unwrap();
+        builder.build()
+    }
+
+    /// Returns the words of some vertices, sorted.
+    fn words(graph: &Graph, vertices: impl Iterator<Item = usize>) -> Vec<&str> {
+        let mut words: Vec<&str> = vertices.map(|v| graph.word(v)).collect();
+        words.sort_unstable();
+        words
+    }
+
+    #[test]
+    fn blocks_and_cut_vertices_are_found() {
+        let graph = test_graph();
+        let tree = graph.block_cut_tree();
+
+        let mut blocks: Vec<Vec<&str>> = tree
+            .blocks
+            .iter()
+            .map(|block| words(&graph, block.iter().copied()))
+            .collect();
+        blocks.sort_unstable();
+        assert_eq!(
+            blocks,
+            [vec!["a", "b", "c"], vec!["c", "d"], vec!["d", "e", "f", "g"], vec!["g", "h"], vec!["i"]]
+        );
+
+        assert_eq!(words(&graph, (0..graph.size()).filter(|&v| tree.is_cut_vertex(v))), ["c", "d", "g"]);
+
+        for (block, vertices) in tree.blocks.iter().enumerate() {
+            for &v in vertices {
+                assert!(tree.vertex_blocks[v].contains(&block));
+            }
+        }
+
+        let square = tree.vertex_blocks[graph.get_index_for_word("e")][0];
+        assert_eq!(words(&graph, tree.cut_vertices(square)), ["d", "g"]);
+    }
+}
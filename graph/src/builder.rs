@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use crate::{one_letter_different, Graph, GraphError};
+
+/// Builds a graph in memory, one word or edge at a time, for when there is
+/// no adjacency file to load it from. Vertices are numbered in the order
+/// their words are first added.
+#[derive(Debug, Default)]
+pub struct GraphBuilder {
+    words: Vec<String>,
+    adjacency_lists: Vec<Vec<usize>>,
+    word_to_index: HashMap<String, usize>,
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a word to the graph, if it is not already there, and returns its
+    /// index. The word must be the same length as the words already added.
+    pub fn add_word(&mut self, word: &str) -> Result<usize, GraphError> {
+        if let Some(&idx) = self.word_to_index.get(word) {
+            return Ok(idx);
+        }
+
+        if let Some(expected) = self.words.first().map(String::len) {
+            if word.len() != expected {
+                return Err(GraphError::WrongLength {
+                    filename: None,
+                    line: None,
+                    word: word.to_string(),
+                    expected,
+                });
+            }
+        }
+
+        let idx = self.words.len();
+        self.words.push(word.to_string());
+        self.adjacency_lists.push(Vec::new());
+        self.word_to_index.insert(word.to_string(), idx);
+        Ok(idx)
+    }
+
+    /// Adds an edge between two words, adding the words too if they are not
+    /// already there. The words do not have to be one letter different. Adding
+    /// an edge which is already there, or from a word to itself, does nothing.
+    pub fn add_edge(&mut self, word1: &str, word2: &str) -> Result<(), GraphError> {
+        let idx1 = self.add_word(word1)?;
+        let idx2 = self.add_word(word2)?;
+
+        if idx1 != idx2 && !self.adjacency_lists[idx1].contains(&idx2) {
+            self.adjacency_lists[idx1].push(idx2);
+            self.adjacency_lists[idx2].push(idx1);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the finished graph, with its components calculated.
+    pub fn build(self) -> Graph {
+        Graph::from_adjacency_lists(&self.words, &self.adjacency_lists)
+    }
+}
+
+impl Graph {
+    /// Returns the graph of a list of words, in which words are adjacent if
+    /// they are one letter different, just as if pre_calc had written its
+    /// adjacency file and it had been loaded. Repeated words are ignored.
+    /// Every pair of words is compared, so this is meant for small graphs.
+    /// The words must all be the same length.
+    pub fn from_words<I, S>(words: I) -> Result<Self, GraphError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = GraphBuilder::new();
+        for word in words {
+            builder.add_word(word.as_ref())?;
+        }
+
+        let words = &builder.words;
+        for idx1 in 0..words.len() {
+            for idx2 in idx1 + 1..words.len() {
+                if one_letter_different(&words[idx1], &words[idx2]) {
+                    builder.adjacency_lists[idx1].push(idx2);
+                    builder.adjacency_lists[idx2].push(idx1);
+                }
+            }
+        }
+
+        Ok(builder.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_words_joins_words_one_letter_different() {
+        let graph = Graph::from_words(["cold", "cord", "card", "ward", "warm", "cold", "tree"]).unwrap();
+
+        assert_eq!(graph.size(), 6);
+        assert_eq!(graph.word_length(), 4);
+
+        let word = |idx: usize| graph.word(idx);
+        let mut edges: Vec<(&str, &str)> = graph
+            .edges()
+            .map(|(a, b)| (word(a).min(word(b)), word(a).max(word(b))))
+            .collect();
+        edges.sort_unstable();
+        assert_eq!(
+            edges,
+            [("card", "cord"), ("card", "ward"), ("cold", "cord"), ("ward", "warm")]
+        );
+
+        assert_eq!(graph.component_of("cold"), graph.component_of("warm"));
+        assert_ne!(graph.component_of("cold"), graph.component_of("tree"));
+    }
+
+    #[test]
+    fn builder_numbers_words_in_order_and_ignores_repeated_edges() {
+        let mut builder = GraphBuilder::new();
+        assert_eq!(builder.add_word("dog").unwrap(), 0);
+        builder.add_edge("dog", "cat").unwrap();
+        builder.add_edge("cat", "dog").unwrap();
+        builder.add_edge("cat", "cat").unwrap();
+        assert_eq!(builder.add_word("cat").unwrap(), 1);
+        builder.add_edge("cat", "cot").unwrap();
+
+        let graph = builder.build();
+        assert_eq!(graph.size(), 3);
+        assert_eq!(graph.neighbours(0).collect::<Vec<_>>(), [1]);
+        assert_eq!(graph["cat"], [0, 2]);
+        assert_eq!(graph.find("cot"), Some(2));
+        assert_eq!(graph.component_vertices(graph.component_of("dog").unwrap()).count(), 3);
+    }
+
+    #[test]
+    fn words_of_the_wrong_length_are_rejected() {
+        let mut builder = GraphBuilder::new();
+        builder.add_word("dog").unwrap();
+        assert!(matches!(
+            builder.add_edge("dog", "dogs"),
+            Err(GraphError::WrongLength { filename: None, line: None, expected: 3, .. })
+        ));
+
+        let error = Graph::from_words(["cold", "warm", "hot"]).unwrap_err();
+        assert_eq!(error.to_string(), "'hot' has 3 letters, but the first word has 4");
+    }
+}
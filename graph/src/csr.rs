@@ -0,0 +1,86 @@
+use std::convert::TryFrom;
+
+use crate::{Graph, ReducedGraph};
+
+/// A compact, read-only copy of the adjacency lists of a graph in compressed
+/// sparse row form. The neighbours of vertex `v` are
+/// `neighbours[offsets[v]..offsets[v + 1]]`, so all the lists are in a single
+/// allocation, and indices are 32 bits rather than 64. This takes much less
+/// memory than a `Vec` per vertex and is far friendlier to the cache, which
+/// matters in the inner loop of a search.
+#[derive(Debug, Clone)]
+pub struct CsrGraph {
+    offsets: Vec<u32>,
+    neighbours: Vec<u32>,
+}
+
+impl CsrGraph {
+    /// Builds a CSR graph from the adjacency list of each vertex in turn.
+    ///
+    /// Panics if the graph is too large for 32-bit indices.
+    pub fn from_adjacency_lists<'a, I>(adjacency_lists: I) -> Self
+    where
+        I: IntoIterator<Item = &'a [usize]>,
+    {
+        let to_u32 = |n: usize| u32::try_from(n).expect("Graph is too large for 32-bit indices");
+
+        let mut offsets = vec![0];
+        let mut neighbours = Vec::new();
+        for adjacency_list in adjacency_lists {
+            neighbours.extend(adjacency_list.iter().map(|&a| to_u32(a)));
+            offsets.push(to_u32(neighbours.len()));
+        }
+
+        offsets.shrink_to_fit();
+        neighbours.shrink_to_fit();
+        Self { offsets, neighbours }
+    }
+
+    /// Returns the offsets and the neighbours, for `Graph` to keep.
+    pub(crate) fn into_parts(self) -> (Vec<u32>, Vec<u32>) {
+        (self.offsets, self.neighbours)
+    }
+
+    /// Returns the number of vertices in the graph.
+    pub fn size(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns the vertices adjacent to `vertex_index`.
+    pub fn neighbours(&self, vertex_index: usize) -> &[u32] {
+        let start = self.offsets[vertex_index] as usize;
+        let end = self.offsets[vertex_index + 1] as usize;
+        &self.neighbours[start..end]
+    }
+
+    /// Returns the number of other vertices that a vertex is connected to.
+    pub fn degree(&self, vertex_index: usize) -> usize {
+        (self.offsets[vertex_index + 1] - self.offsets[vertex_index]) as usize
+    }
+
+    /// Returns the total length of all the adjacency lists, which is twice
+    /// the number of edges.
+    pub fn num_adjacencies(&self) -> usize {
+        self.neighbours.len()
+    }
+}
+
+impl Graph {
+    /// Returns a CSR copy of the graph's adjacency lists. Vertex indices are
+    /// the same in both. The graph already stores them in this form, so this
+    /// only has to copy two arrays.
+    pub fn to_csr(&self) -> CsrGraph {
+        CsrGraph {
+            offsets: self.offsets.to_vec(),
+            neighbours: self.neighbours.to_vec(),
+        }
+    }
+}
+
+impl ReducedGraph {
+    /// Returns a CSR copy of the reduced graph's adjacency lists. Vertex
+    /// indices are the same in both.
+    pub fn to_csr(&self) -> CsrGraph {
+        CsrGraph::from_adjacency_lists(self.vertices.iter().map(|v| v.adjacency_list.as_slice()))
+    }
+}
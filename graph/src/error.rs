@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// The reasons a graph can fail to load or to be built. Line numbers start
+/// at 1.
+#[derive(Debug)]
+pub enum GraphError {
+    Io { filename: PathBuf, error: io::Error },
+    /// The file has no words in it at all.
+    Empty { filename: PathBuf },
+    /// A word appears as the first word of more than one line.
+    DuplicateWord { filename: PathBuf, line: usize, word: String },
+    /// A word is not the same length as the first word in the file, or the
+    /// first word added to a `GraphBuilder`, which has no file or line.
+    WrongLength { filename: Option<PathBuf>, line: Option<usize>, word: String, expected: usize },
+    /// A word appears in an adjacency list but not as the first word of any
+    /// line, which usually means the file is incomplete.
+    UnknownWord { filename: PathBuf, line: usize, word: String },
+    /// A binary graph file was written in a different version of the format.
+    WrongVersion { filename: PathBuf, version: u32, expected: u32 },
+    /// A binary graph file does not match its checksum, so it is damaged.
+    WrongChecksum { filename: PathBuf },
+    /// A binary graph file is not laid out as its header says.
+    Malformed { filename: PathBuf, reason: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Io { filename, error } => write!(f, "{:?}: {}", filename, error),
+            GraphError::Empty { filename } => write!(f, "{:?}: the file has no words in it", filename),
+            GraphError::DuplicateWord { filename, line, word } => write!(
+                f,
+                "{:?}, line {}: '{}' already has a line of its own",
+                filename, line, word
+            ),
+            GraphError::WrongLength { filename, line, word, expected } => {
+                if let (Some(filename), Some(line)) = (filename, line) {
+                    write!(f, "{:?}, line {}: ", filename, line)?;
+                }
+                write!(
+                    f,
+                    "'{}' has {} letters, but the first word has {}",
+                    word,
+                    word.len(),
+                    expected
+                )
+            }
+            GraphError::UnknownWord { filename, line, word } => write!(
+                f,
+                "{:?}, line {}: '{}' does not have a line of its own, the file may be incomplete",
+                filename, line, word
+            ),
+            GraphError::WrongVersion { filename, version, expected } => write!(
+                f,
+                "{:?}: the file is version {} of the binary graph format but version {} is needed, run pre-calc again",
+                filename, version, expected
+            ),
+            GraphError::WrongChecksum { filename } => {
+                write!(f, "{:?}: the checksum does not match, the file is damaged", filename)
+            }
+            GraphError::Malformed { filename, reason } => write!(f, "{:?}: {}", filename, reason),
+        }
+    }
+}
+
+impl Error for GraphError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GraphError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
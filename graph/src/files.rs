@@ -0,0 +1,34 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes a file under a temporary name, '{filename}.tmp', and then renames it
+/// into place. A crash part way through therefore never leaves a partial file
+/// behind, and any previous version of the file stays intact until the new one
+/// is complete.
+pub fn atomic_write(filename: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_filename = filename.as_os_str().to_owned();
+    temp_filename.push(".tmp");
+    let temp_filename = PathBuf::from(temp_filename);
+
+    {
+        let mut file = File::create(&temp_filename)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&temp_filename, filename)
+}
+
+/// Calculates the 64-bit FNV-1a hash of some bytes, which the output files
+/// record so that damaged ones can be detected and not trusted. This is not
+/// cryptographic, it only needs to catch files which have been truncated or
+/// corrupted on disk.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(PRIME)
+    })
+}
@@ -1,67 +1,79 @@
-use std::collections::HashSet;
-use std::io::{self, BufRead, BufReader};
+use std::convert::TryFrom;
+use std::io::{BufRead, BufReader};
+use std::ops::Index;
 use std::{collections::HashMap, fs::File, path::Path};
 
+mod binary_file;
+mod block_cut_tree;
+mod builder;
+mod csr;
+mod error;
+mod files;
+mod reduction;
 mod relative_directories;
-
+mod storage;
+
+pub use binary_file::BINARY_FORMAT_VERSION;
+pub use block_cut_tree::BlockCutTree;
+pub use builder::GraphBuilder;
+pub use csr::CsrGraph;
+pub use error::GraphError;
+pub use files::{atomic_write, checksum};
+pub use reduction::{ReducedGraph, ReducedVertex, TrimmedPendant, VertexLocation};
 pub use relative_directories::RelativeDirectories;
 
-#[derive(Debug)]
-pub struct Vertex {
-    pub word: String,
-    pub adjacency_list: Vec<usize>,
-    pub component: usize,
-}
-
-impl Vertex {
-    /// Returns the number of other vertices that this vertex is connected to.
-    fn degree(&self) -> usize {
-        self.adjacency_list.len()
-    }
+use storage::Array;
 
-    /// Return true if this is a leaf node.
-    fn is_leaf(&self) -> bool {
-        self.degree() == 1
-    }
-}
-
-impl From<String> for Vertex {
-    fn from(word: String) -> Self {
-        Self {
-            word,
-            adjacency_list: Vec::new(),
-            component: usize::MAX,
-        }
-    }
-}
+/// The index of a vertex in a `Graph`, from 0 to `size() - 1`.
+pub type VertexId = usize;
 
 /// Represents a graph of words of length N. This is really a forest, because
 /// there may be (in fact, probably are) multiple components within the graph.
+///
+/// The graph is stored as a handful of flat arrays rather than a structure per
+/// vertex, in the same layout as a binary graph file, so that a graph loaded
+/// from one can borrow its arrays straight from the memory-mapped file.
 #[derive(Debug)]
 pub struct Graph {
-    pub vertices: Vec<Vertex>,
-    // Provide a fast way of looking up the index of a word.
-    word_to_index: HashMap<String, usize>,
+    word_length: usize,
+    // The words of all the vertices one after another. They all have the same
+    // length and so need no separators.
+    words: Array<u8>,
+    // The adjacency lists in compressed sparse row form, as in `CsrGraph`.
+    offsets: Array<u32>,
+    neighbours: Array<u32>,
+    components: Array<u32>,
+    // The indexes of the vertices in order of their words, so that words can
+    // be looked up by binary search without keeping a second copy of them.
+    words_in_order: Array<u32>,
 }
 
 impl Graph {
-    /// Returns the length of the words in this graph.
+    /// Returns the length of the words in this graph, or 0 if it is empty.
     pub fn word_length(&self) -> usize {
-        self.vertices[0].word.len()
+        self.word_length
     }
 
     /// Returns the number of vertices in the graph.
     pub fn size(&self) -> usize {
-        self.vertices.len()
+        self.offsets.len() - 1
     }
 
     /// Reads in the specified difference file (e.g. "one_letter_different_05.txt")
     /// and returns a graph with all its vertices correctly linked and its
-    /// components calculated.
-    pub fn load_from_adjacency_file<P: AsRef<Path>>(filename: P) -> io::Result<Self> {
-        let f = File::open(filename)?;
+    /// components calculated. Blank lines and Windows line endings are allowed,
+    /// but a file which is malformed, for example because it was only partly
+    /// written, is rejected with the line and word where the problem is.
+    pub fn load_from_adjacency_file<P: AsRef<Path>>(filename: P) -> Result<Self, GraphError> {
+        let filename = filename.as_ref();
+        let io_error = |error| GraphError::Io {
+            filename: filename.to_path_buf(),
+            error,
+        };
+
+        let f = File::open(filename).map_err(io_error)?;
         let rdr = BufReader::new(f);
-        let lines = rdr.lines().collect::<io::Result<Vec<String>>>()?;
+        let lines = rdr.lines().collect::<Result<Vec<String>, _>>().map_err(io_error)?;
 
         // Each line consists of 2 or more words.
         // The first word is the 'anchor', and the remaining words are the 'adjacency list':
@@ -70,36 +82,101 @@ impl Graph {
         //
         // We can use this to build a graph and then calculate its components. A component
         // is a set of words which can all be reached by N-step transformations.
-        let mut graph = Graph {
-            vertices: Vec::new(),
-            word_to_index: HashMap::new(),
-        };
+        let mut words = Vec::new();
+        let mut word_to_index = HashMap::new();
 
-        // Create a vertex for each anchor word. We have to do this first
-        // so that they are all created before we calculate the adjacency list
-        // (we need the index into the vertices vector to do that).
+        // Pair each non-blank line with its line number, for error messages.
+        let lines: Vec<(usize, &str)> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.as_str()))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect();
+
+        // Number each anchor word. We have to do this first so that they are
+        // all numbered before we calculate the adjacency lists.
         // When this is complete there will be a vertex in the graph for
         // every word in the file (which really means every anchor word - it
         // should be apparent that there are no reachable words which are not
         // also anchor words, because for any word pair "A B" then "B A"
         // will also appear in the file).
-        for line in &lines {
-            let anchor_word = line.split(' ').next().unwrap();
-            graph.add_vertex(anchor_word);
+        for &(line_number, line) in &lines {
+            let anchor_word = line.split_whitespace().next().unwrap_or_default();
+
+            if let Some(expected) = words.first().map(|w: &&str| w.len()) {
+                if anchor_word.len() != expected {
+                    return Err(GraphError::WrongLength {
+                        filename: Some(filename.to_path_buf()),
+                        line: Some(line_number),
+                        word: anchor_word.to_string(),
+                        expected,
+                    });
+                }
+            }
+
+            if word_to_index.insert(anchor_word, words.len()).is_some() {
+                return Err(GraphError::DuplicateWord {
+                    filename: filename.to_path_buf(),
+                    line: line_number,
+                    word: anchor_word.to_string(),
+                });
+            }
+
+            words.push(anchor_word);
         }
 
-        for line in lines {
-            let mut words_in_line = line.split(' ').filter(|w| !w.is_empty());
-            let anchor_word = words_in_line.next().unwrap();
-            let anchor_word_index = graph.get_index_for_word(anchor_word);
+        if words.is_empty() {
+            return Err(GraphError::Empty {
+                filename: filename.to_path_buf(),
+            });
+        }
 
-            graph.vertices[anchor_word_index].adjacency_list =
-                words_in_line.map(|w| graph.get_index_for_word(w)).collect();
+        let mut adjacency_lists = vec![Vec::new(); words.len()];
+        for &(line_number, line) in &lines {
+            let mut words_in_line = line.split_whitespace();
+            let anchor_word = words_in_line.next().unwrap_or_default();
+            let anchor_word_index = word_to_index[anchor_word];
+
+            adjacency_lists[anchor_word_index] = words_in_line
+                .map(|w| {
+                    word_to_index.get(w).copied().ok_or_else(|| GraphError::UnknownWord {
+                        filename: filename.to_path_buf(),
+                        line: line_number,
+                        word: w.to_string(),
+                    })
+                })
+                .collect::<Result<Vec<usize>, _>>()?;
         }
 
-        graph.calculate_components();
+        Ok(Graph::from_adjacency_lists(&words, &adjacency_lists))
+    }
+
+    /// Returns the graph with the given words and adjacency lists, indexing
+    /// the words and calculating the components. The words must all be the
+    /// same length.
+    ///
+    /// Panics if the graph is too large for 32-bit indices.
+    fn from_adjacency_lists<S: AsRef<str>>(words: &[S], adjacency_lists: &[Vec<usize>]) -> Self {
+        let to_u32 = |n: usize| u32::try_from(n).expect("Graph is too large for 32-bit indices");
 
-        Ok(graph)
+        let csr = CsrGraph::from_adjacency_lists(adjacency_lists.iter().map(Vec::as_slice));
+        let (offsets, neighbours) = csr.into_parts();
+
+        let mut graph = Graph {
+            word_length: words.first().map_or(0, |w| w.as_ref().len()),
+            words: Array::Owned(words.iter().flat_map(|w| w.as_ref().bytes()).collect()),
+            offsets: Array::Owned(offsets),
+            neighbours: Array::Owned(neighbours),
+            components: Array::Owned(Vec::new()),
+            words_in_order: Array::Owned(Vec::new()),
+        };
+
+        let mut words_in_order: Vec<u32> = (0..words.len()).map(to_u32).collect();
+        words_in_order.sort_unstable_by_key(|&idx| words[idx as usize].as_ref());
+        graph.words_in_order = Array::Owned(words_in_order);
+        graph.components = Array::Owned(graph.calculate_components());
+
+        graph
     }
 
     /// Analyze the components in the graph, returning a map of component -> num vertices
@@ -107,8 +184,8 @@ impl Graph {
     pub fn components(&self) -> Vec<Component> {
         let mut map = HashMap::<usize, usize>::new();
 
-        for v in &self.vertices {
-            let entry = map.entry(v.component).or_insert(0);
+        for idx in 0..self.size() {
+            let entry = map.entry(self.component(idx)).or_insert(0);
             *entry += 1;
         }
 
@@ -120,62 +197,158 @@ impl Graph {
             })
             .collect();
 
-        v.sort_unstable_by(|a, b| b.num_vertices.cmp(&a.num_vertices));
+        v.sort_unstable_by_key(|c| std::cmp::Reverse(c.num_vertices));
         v
     }
 
+    /// Returns the subgraph induced by the specified vertices, i.e. those
+    /// vertices and all the edges between them. Vertex `i` in the subgraph
+    /// is vertex `vertex_indices[i]` in this graph.
+    pub fn subgraph(&self, vertex_indices: &[usize]) -> Graph {
+        let new_indexes: HashMap<usize, usize> = vertex_indices
+            .iter()
+            .enumerate()
+            .map(|(new_idx, &idx)| (idx, new_idx))
+            .collect();
+
+        let words: Vec<&str> = vertex_indices.iter().map(|&idx| self.word(idx)).collect();
+        let adjacency_lists: Vec<Vec<usize>> = vertex_indices
+            .iter()
+            .map(|&idx| self.neighbours(idx).filter_map(|a| new_indexes.get(&a).copied()).collect())
+            .collect();
+
+        Graph::from_adjacency_lists(&words, &adjacency_lists)
+    }
+
+    /// Returns the word of a vertex.
+    pub fn word(&self, vertex: VertexId) -> &str {
+        std::str::from_utf8(self.word_bytes(vertex)).expect("Words are checked when the graph is loaded")
+    }
+
+    fn word_bytes(&self, vertex: VertexId) -> &[u8] {
+        &self.words[vertex * self.word_length..(vertex + 1) * self.word_length]
+    }
+
+    /// Returns the index of a word, or None if it is not in the graph.
+    pub fn find(&self, word: &str) -> Option<VertexId> {
+        self.words_in_order
+            .binary_search_by(|&idx| self.word_bytes(idx as usize).cmp(word.as_bytes()))
+            .ok()
+            .map(|i| self.words_in_order[i] as usize)
+    }
+
+    /// Returns the index of a word, panicking if it is not in the graph. Use
+    /// `find` for words which may not be.
+    pub fn get_index_for_word(&self, word: &str) -> VertexId {
+        match self.find(word) {
+            Some(idx) => idx,
+            None => panic!("'{}' is not in the graph", word),
+        }
+    }
+
+    /// Returns the adjacency list of a vertex, as stored.
+    fn adjacency_list(&self, vertex: VertexId) -> &[u32] {
+        &self.neighbours[self.offsets[vertex] as usize..self.offsets[vertex + 1] as usize]
+    }
+
+    /// Iterates over the neighbours of a vertex.
+    pub fn neighbours(&self, vertex: VertexId) -> impl ExactSizeIterator<Item = VertexId> + Clone + '_ {
+        self.adjacency_list(vertex).iter().map(|&a| a as usize)
+    }
+
+    /// Returns true if two vertices are adjacent.
+    pub fn is_adjacent(&self, vertex1: VertexId, vertex2: VertexId) -> bool {
+        self.neighbours(vertex1).any(|a| a == vertex2)
+    }
 
-    fn add_vertex<S: Into<String>>(&mut self, word: S) {
-        let word = word.into();
+    /// Returns the number of other vertices that a vertex is connected to.
+    pub fn degree(&self, vertex: VertexId) -> usize {
+        self.adjacency_list(vertex).len()
+    }
 
-        let vertex = word.clone().into();
-        self.vertices.push(vertex);
+    /// Return true if a vertex is a leaf node.
+    pub fn is_leaf(&self, vertex: VertexId) -> bool {
+        self.degree(vertex) == 1
+    }
+
+    /// Iterates over the words which are one step from `word`, or returns None
+    /// if it is not in the graph.
+    pub fn neighbour_words(&self, word: &str) -> Option<impl Iterator<Item = &str> + '_> {
+        let idx = self.find(word)?;
+        Some(self.neighbours(idx).map(move |a| self.word(a)))
+    }
 
-        self.word_to_index.insert(word, self.vertices.len() - 1);
+    /// Iterates over every edge in the graph once, as the pair of vertices it
+    /// joins with the lower index first.
+    pub fn edges(&self) -> impl Iterator<Item = (VertexId, VertexId)> + '_ {
+        (0..self.size()).flat_map(move |idx| {
+            self.neighbours(idx).filter(move |&a| idx < a).map(move |a| (idx, a))
+        })
     }
 
-    // TODO: Implement Index<str>.
-    pub fn get_index_for_word(&self, word: &str) -> usize {
-        self.word_to_index[word]
+    /// Returns the number of the component a vertex is in.
+    pub fn component(&self, vertex: VertexId) -> usize {
+        self.components[vertex] as usize
+    }
+
+    /// Returns the number of the component a word is in, or None if it is not
+    /// in the graph.
+    pub fn component_of(&self, word: &str) -> Option<usize> {
+        self.find(word).map(|idx| self.component(idx))
+    }
+
+    /// Iterates over the vertices in a component.
+    pub fn component_vertices(&self, component: usize) -> impl Iterator<Item = VertexId> + '_ {
+        (0..self.size()).filter(move |&idx| self.component(idx) == component)
     }
 
     /// Calculates the components of the graph. All the vertices in the graph are examined,
     /// and all those that are reachable from each other (in 1 or more steps) are assigned
-    /// the same component number.
-    fn calculate_components(&mut self) {
+    /// the same component number. Components are numbered in order of their first vertex,
+    /// with a single sweep over the vertices and an iterative breadth-first search from
+    /// each one not yet in a component, so this is linear in the size of the graph and
+    /// cannot overflow the stack.
+    fn calculate_components(&self) -> Vec<u32> {
+        let mut components = vec![u32::MAX; self.size()];
         let mut next_component_number = 0;
+        let mut queue = Vec::new();
+
+        for idx in 0..self.size() {
+            if components[idx] != u32::MAX {
+                continue;
+            }
 
-        while let Some(idx) = self.vertices.iter().position(|v| v.component == usize::MAX) {
-            // First find all the vertices in this component and record their indexes in `seen`.
-            // This is a read-only operation.
-            let mut seen = HashSet::new();
-            self.dfs(&mut seen, idx);
+            components[idx] = next_component_number;
+            queue.clear();
+            queue.push(idx);
+            let mut head = 0;
 
-            // Then set the component on all those nodes.
-            for i in 0..self.vertices.len() {
-                if seen.contains(&i) {
-                    self.vertices[i].component = next_component_number;
+            while head < queue.len() {
+                let v = queue[head];
+                head += 1;
+
+                for a in self.neighbours(v) {
+                    if components[a] == u32::MAX {
+                        components[a] = next_component_number;
+                        queue.push(a);
+                    }
                 }
             }
 
             next_component_number += 1;
         }
+
+        components
     }
+}
 
-    fn dfs(&self, seen: &mut HashSet<usize>, idx: usize) {
-        seen.insert(idx);
-        let v = &self.vertices[idx];
+impl Index<&str> for Graph {
+    type Output = [u32];
 
-        for i2 in &v.adjacency_list {
-            if seen.contains(i2) {
-                continue;
-            }
-
-            let v2 = &self.vertices[idx];
-            if v2.component == usize::MAX {
-                self.dfs(seen, *i2);
-            }
-        }
+    /// Returns the adjacency list of a word, panicking if it is not in the
+    /// graph.
+    fn index(&self, word: &str) -> &[u32] {
+        self.adjacency_list(self.get_index_for_word(word))
     }
 }
 
@@ -202,7 +375,7 @@ pub struct WordLengthStatistics {
 
 impl WordLengthStatistics {
     pub fn largest_component_word_count(&self) -> usize {
-        self.largest_five_component_counts[0]
+        self.largest_five_component_counts.first().copied().unwrap_or(0)
     }
 
     pub fn largest_component_percent_of_total(&self) -> f64 {
@@ -221,11 +394,29 @@ impl WordLengthStatistics {
     }
 }
 
+/// Returns true if two words of the same length differ in exactly one letter,
+/// which is the rule for two words to be adjacent in a word graph.
+pub fn one_letter_different(w1: &str, w2: &str) -> bool {
+    assert_eq!(w1.len(), w2.len());
+
+    let mut num_diffs = 0;
+    for (a, b) in w1.chars().zip(w2.chars()) {
+        if a != b {
+            num_diffs += 1;
+        }
+        if num_diffs == 2 {
+            return false;
+        }
+    }
+
+    num_diffs == 1
+}
+
 /// Calculates various interesting statistics for a word graph.
 pub fn calculate_graph_stats(graph: &Graph) -> WordLengthStatistics {
     let mut stats = WordLengthStatistics {
-        word_length: graph.vertices[0].word.len(),
-        total_word_count: graph.vertices.len(),
+        word_length: graph.word_length(),
+        total_word_count: graph.size(),
         ..Default::default()
     };
 
@@ -242,22 +433,120 @@ pub fn calculate_graph_stats(graph: &Graph) -> WordLengthStatistics {
     stats.largest_five_component_counts =
         components.iter().take(5).map(|c| c.num_vertices).collect();
 
-    let largest_component_number = components[0].number;
-    stats.largest_component_leaf_count = graph
-        .vertices
-        .iter()
-        .filter(|v| v.component == largest_component_number && v.is_leaf())
-        .count();
-
-    if let Some(largest_vertex) = graph.vertices.iter().max_by_key(|v| v.adjacency_list.len()) {
-        stats.max_adjacents_count = largest_vertex.adjacency_list.len();
-        stats.max_adjacents_word = largest_vertex.word.clone();
-        stats.max_adjacencts_list = largest_vertex
-            .adjacency_list
-            .iter()
-            .map(|idx| graph.vertices[*idx].word.clone())
+    if let Some(largest_component) = components.first() {
+        stats.largest_component_leaf_count = graph
+            .component_vertices(largest_component.number)
+            .filter(|&idx| graph.is_leaf(idx))
+            .count();
+    }
+
+    if let Some(largest_vertex) = (0..graph.size()).max_by_key(|&idx| graph.degree(idx)) {
+        stats.max_adjacents_count = graph.degree(largest_vertex);
+        stats.max_adjacents_word = graph.word(largest_vertex).to_string();
+        stats.max_adjacencts_list = graph
+            .neighbours(largest_vertex)
+            .map(|idx| graph.word(idx).to_string())
             .collect();
     }
 
     stats
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Labels the components the way `calculate_components` used to, by a
+    /// recursive depth-first search from the first vertex not yet labelled.
+    fn recursive_components(graph: &Graph) -> Vec<usize> {
+        fn dfs(graph: &Graph, components: &mut Vec<usize>, idx: usize, component: usize) {
+            components[idx] = component;
+            for a in graph.neighbours(idx) {
+                if components[a] == usize::MAX {
+                    dfs(graph, components, a, component);
+                }
+            }
+        }
+
+        let mut components = vec![usize::MAX; graph.size()];
+        let mut next_component_number = 0;
+        while let Some(idx) = components.iter().position(|&c| c == usize::MAX) {
+            dfs(graph, &mut components, idx, next_component_number);
+            next_component_number += 1;
+        }
+        components
+    }
+
+    #[test]
+    fn components_match_the_recursive_labelling() {
+        let mut rng = StdRng::seed_from_u64(21);
+
+        for _ in 0..200 {
+            let size = rng.gen_range(1..=60);
+            let mut builder = GraphBuilder::new();
+            for idx in 0..size {
+                builder.add_word(&format!("{:02}", idx)).unwrap();
+            }
+            for _ in 0..rng.gen_range(0..=size) {
+                let a = rng.gen_range(0..size);
+                let b = rng.gen_range(0..size);
+                builder.add_edge(&format!("{:02}", a), &format!("{:02}", b)).unwrap();
+            }
+
+            let graph = builder.build();
+            let components: Vec<usize> = (0..graph.size()).map(|idx| graph.component(idx)).collect();
+            assert_eq!(components, recursive_components(&graph));
+        }
+    }
+
+    #[test]
+    fn components_of_a_long_path_do_not_overflow_the_stack() {
+        let mut builder = GraphBuilder::new();
+        for idx in 1..200_000 {
+            builder.add_edge(&format!("{:06}", idx - 1), &format!("{:06}", idx)).unwrap();
+        }
+
+        let graph = builder.build();
+        assert!((0..graph.size()).all(|idx| graph.component(idx) == 0));
+    }
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(name)
+    }
+
+    #[test]
+    fn blank_lines_and_windows_line_endings_are_allowed() {
+        for name in &["blank_lines.txt", "crlf.txt"] {
+            let graph = Graph::load_from_adjacency_file(fixture(name)).unwrap();
+
+            assert_eq!(graph.size(), 3, "{}", name);
+            assert_eq!(graph.word_length(), 4, "{}", name);
+            assert_eq!(
+                graph.neighbour_words("cord").unwrap().collect::<Vec<_>>(),
+                ["cold", "card"],
+                "{}",
+                name
+            );
+            assert_eq!(graph.components().len(), 1, "{}", name);
+        }
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let result = Graph::load_from_adjacency_file(fixture("unknown_word.txt"));
+        assert!(matches!(result, Err(GraphError::UnknownWord { line: 2, ref word, .. }) if word == "card"));
+
+        let result = Graph::load_from_adjacency_file(fixture("duplicate_word.txt"));
+        assert!(matches!(result, Err(GraphError::DuplicateWord { line: 3, ref word, .. }) if word == "cold"));
+
+        let result = Graph::load_from_adjacency_file(fixture("wrong_length.txt"));
+        assert!(matches!(
+            result,
+            Err(GraphError::WrongLength { line: Some(3), ref word, expected: 4, .. }) if word == "cards"
+        ));
+
+        let result = Graph::load_from_adjacency_file(fixture("empty.txt"));
+        assert!(matches!(result, Err(GraphError::Empty { .. })));
+    }
+}
@@ -0,0 +1,410 @@
+use crate::Graph;
+
+/// A vertex of a `ReducedGraph`. It stands for a run of one or more vertices
+/// of the original graph which any path must either traverse completely or
+/// end inside.
+#[derive(Debug)]
+pub struct ReducedVertex {
+    /// The vertices of the original graph that this vertex stands for, in
+    /// order along the run. A vertex that was not contracted has a run of 1.
+    pub run: Vec<usize>,
+    /// The reduced vertex adjacent to the first vertex of `run`, if it is a
+    /// contracted run of degree-2 vertices.
+    pub first_neighbour: Option<usize>,
+    /// The reduced vertex adjacent to the last vertex of `run`, if it is a
+    /// contracted run of degree-2 vertices.
+    pub last_neighbour: Option<usize>,
+    pub adjacency_list: Vec<usize>,
+}
+
+impl ReducedVertex {
+    /// The number of original vertices that this vertex stands for.
+    pub fn weight(&self) -> usize {
+        self.run.len()
+    }
+}
+
+/// A pendant run (a leaf plus any degree-2 vertices leading to it) which
+/// was removed because a heavier one hangs off the same vertex.
+#[derive(Debug)]
+pub struct TrimmedPendant {
+    /// The vertices of the original graph, starting at the attached end
+    /// and finishing at the leaf.
+    pub run: Vec<usize>,
+    /// The reduced vertex that the run was attached to.
+    pub attached_to: usize,
+}
+
+/// Where a vertex of the original graph ended up after reduction.
+#[derive(Debug, Clone, Copy)]
+pub enum VertexLocation {
+    /// At `position` within the run of a reduced vertex.
+    Reduced { vertex: usize, position: usize },
+    /// At `position` within the run of a trimmed pendant.
+    Trimmed { pendant: usize, position: usize },
+}
+
+/// A smaller, vertex-weighted version of a graph that has the same longest
+/// paths. Maximal runs of vertices of degree 1 or 2 are contracted into a single
+/// vertex whose weight is the length of the run, and of the pendant runs hanging
+/// off each vertex only the two heaviest are kept. A simple path can use at most
+/// two of them, and only by starting in one and ending in the other.
+#[derive(Debug)]
+pub struct ReducedGraph {
+    pub vertices: Vec<ReducedVertex>,
+    pub trimmed: Vec<TrimmedPendant>,
+    /// The location of each vertex of the original graph.
+    pub locations: Vec<VertexLocation>,
+}
+
+/// A run of vertices found while reducing the graph, before the reduced
+/// vertex numbers are known. Neighbours are original vertex indexes.
+struct Run {
+    run: Vec<usize>,
+    first_neighbour: Option<usize>,
+    last_neighbour: Option<usize>,
+}
+
+impl Run {
+    fn is_pendant(&self) -> bool {
+        self.first_neighbour.is_some() && self.last_neighbour.is_none()
+    }
+}
+
+impl ReducedGraph {
+    /// Returns the number of vertices in the reduced graph.
+    pub fn size(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Expands a path through the reduced graph back into a path through the
+    /// original graph, orienting each run according to the vertices either
+    /// side of it.
+    pub fn expand_path(&self, path: &[usize]) -> Vec<usize> {
+        let mut expanded = Vec::new();
+
+        for (i, &reduced_idx) in path.iter().enumerate() {
+            let vertex = &self.vertices[reduced_idx];
+            let previous = if i > 0 { Some(path[i - 1]) } else { None };
+            let next = path.get(i + 1).copied();
+
+            let forwards = match (previous, next) {
+                (Some(previous), _) => vertex.first_neighbour == Some(previous),
+                (None, Some(next)) => vertex.last_neighbour == Some(next),
+                (None, None) => true,
+            };
+
+            if forwards {
+                expanded.extend(vertex.run.iter());
+            } else {
+                expanded.extend(vertex.run.iter().rev());
+            }
+        }
+
+        expanded
+    }
+}
+
+impl Graph {
+    /// Reduces the graph by contracting runs of degree-2 vertices and trimming
+    /// surplus leaves. See `ReducedGraph`.
+    pub fn reduce(&self) -> ReducedGraph {
+        let runs = self.find_runs();
+
+        // Pendants hanging off the same vertex. Only the two heaviest can
+        // ever be useful, as the ends of a path.
+        let mut pendants_by_attachment = vec![Vec::new(); self.size()];
+        for (run_number, run) in runs.iter().enumerate() {
+            if run.is_pendant() {
+                pendants_by_attachment[run.first_neighbour.unwrap()].push(run_number);
+            }
+        }
+
+        let mut is_trimmed = vec![false; runs.len()];
+        for pendants in &mut pendants_by_attachment {
+            pendants.sort_by_key(|&run_number| std::cmp::Reverse(runs[run_number].run.len()));
+            for &run_number in pendants.iter().skip(2) {
+                is_trimmed[run_number] = true;
+            }
+        }
+
+        // Number the reduced vertices. Every original vertex which is not in
+        // a run stands for itself.
+        let mut reduced_index = vec![usize::MAX; self.size()];
+        let mut vertices = Vec::new();
+        let mut in_run = vec![false; self.size()];
+
+        for (run_number, run) in runs.iter().enumerate() {
+            for &v in &run.run {
+                in_run[v] = true;
+            }
+
+            if !is_trimmed[run_number] {
+                for &v in &run.run {
+                    reduced_index[v] = vertices.len();
+                }
+                vertices.push(ReducedVertex {
+                    run: run.run.clone(),
+                    first_neighbour: run.first_neighbour,
+                    last_neighbour: run.last_neighbour,
+                    adjacency_list: Vec::new(),
+                });
+            }
+        }
+
+        for v in (0..self.size()).filter(|&v| !in_run[v]) {
+            reduced_index[v] = vertices.len();
+            vertices.push(ReducedVertex {
+                run: vec![v],
+                first_neighbour: None,
+                last_neighbour: None,
+                adjacency_list: Vec::new(),
+            });
+        }
+
+        // Now that the numbering is known, translate the neighbours of each
+        // run and build the adjacency lists.
+        for vertex in &mut vertices {
+            vertex.first_neighbour = vertex.first_neighbour.map(|v| reduced_index[v]);
+            vertex.last_neighbour = vertex.last_neighbour.map(|v| reduced_index[v]);
+        }
+
+        for (reduced_idx, vertex) in vertices.iter_mut().enumerate() {
+            let mut adjacency_list: Vec<usize> = vertex
+                .run
+                .iter()
+                .flat_map(|&v| self.neighbours(v))
+                .map(|a| reduced_index[a])
+                .filter(|&r| r != usize::MAX && r != reduced_idx)
+                .collect();
+            adjacency_list.sort_unstable();
+            adjacency_list.dedup();
+            vertex.adjacency_list = adjacency_list;
+        }
+
+        let mut locations = vec![VertexLocation::Reduced { vertex: 0, position: 0 }; self.size()];
+        for (reduced_idx, vertex) in vertices.iter().enumerate() {
+            for (position, &v) in vertex.run.iter().enumerate() {
+                locations[v] = VertexLocation::Reduced {
+                    vertex: reduced_idx,
+                    position,
+                };
+            }
+        }
+
+        let mut trimmed = Vec::new();
+        for (run_number, run) in runs.into_iter().enumerate() {
+            if is_trimmed[run_number] {
+                for (position, &v) in run.run.iter().enumerate() {
+                    locations[v] = VertexLocation::Trimmed {
+                        pendant: trimmed.len(),
+                        position,
+                    };
+                }
+                trimmed.push(TrimmedPendant {
+                    attached_to: reduced_index[run.first_neighbour.unwrap()],
+                    run: run.run,
+                });
+            }
+        }
+
+        ReducedGraph {
+            vertices,
+            trimmed,
+            locations,
+        }
+    }
+
+    /// Finds the maximal runs of vertices with degree 1 or 2. Each is a simple
+    /// path; runs which form a cycle on their own are left alone, because
+    /// contracting them would lose the ability to enter them at any point.
+    /// Pendant runs are oriented so that their attached end comes first.
+    fn find_runs(&self) -> Vec<Run> {
+        let is_run_vertex = |v: usize| matches!(self.degree(v), 1 | 2);
+        let mut seen = vec![false; self.size()];
+        let mut runs = Vec::new();
+
+        for v in 0..self.size() {
+            if seen[v] || !is_run_vertex(v) {
+                continue;
+            }
+
+            // Walk to one end of the run. If we arrive back where we
+            // started then the run is a cycle.
+            let mut end = v;
+            let mut previous = usize::MAX;
+            let mut is_cycle = false;
+            loop {
+                let next = self.neighbours(end).find(|&a| a != previous && is_run_vertex(a));
+
+                match next {
+                    Some(next) if next == v => {
+                        is_cycle = true;
+                        break;
+                    }
+                    Some(next) => {
+                        previous = end;
+                        end = next;
+                    }
+                    None => break,
+                }
+            }
+
+            if is_cycle {
+                let mut u = v;
+                let mut previous = usize::MAX;
+                while !seen[u] {
+                    seen[u] = true;
+                    let next = self.neighbours(u).find(|&a| a != previous && is_run_vertex(a)).unwrap();
+                    previous = u;
+                    u = next;
+                }
+                continue;
+            }
+
+            // Now walk back from that end to the other one, recording the run.
+            let mut run = vec![end];
+            seen[end] = true;
+            let mut previous = usize::MAX;
+            let mut current = end;
+            while let Some(next) = self
+                .neighbours(current)
+                .find(|&a| a != previous && !seen[a] && is_run_vertex(a))
+            {
+                seen[next] = true;
+                run.push(next);
+                previous = current;
+                current = next;
+            }
+
+            let external_neighbours = |v: usize| self.neighbours(v).filter(move |&a| !is_run_vertex(a));
+
+            let mut first_neighbour = external_neighbours(run[0]).next();
+            let mut last_neighbour = if run.len() == 1 {
+                external_neighbours(run[0]).nth(1)
+            } else {
+                external_neighbours(run[run.len() - 1]).next()
+            };
+
+            if first_neighbour.is_none() && last_neighbour.is_some() {
+                run.reverse();
+                std::mem::swap(&mut first_neighbour, &mut last_neighbour);
+            }
+
+            runs.push(Run {
+                run,
+                first_neighbour,
+                last_neighbour,
+            });
+        }
+
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Graph, GraphBuilder, VertexLocation};
+
+    /// Two hubs, 'p' and 'r', joined directly, through 'q' and 's', and
+    /// through a run of three vertices. Three pendant runs of lengths 1, 2
+    /// and 3 hang off 'p', and there is a separate triangle.
+    fn test_graph() -> Graph {
+        let mut builder = GraphBuilder::new();
+        let edges = [
+            ("p", "q"), ("q", "r"), ("r", "s"), ("s", "p"), ("p", "r"),
+            ("p", "x"), ("x", "y"), ("y", "z"), ("z", "r"),
+            ("p", "l"),
+            ("p", "m"), ("m", "n"),
+            ("p", "t"), ("t", "u"), ("u", "v"),
+            ("a", "b"), ("b", "c"), ("c", "a"),
+        ];
+        for (word1, word2) in edges {
+            builder.add_edge(word1, word2).unwrap();
+        }
+        builder.build()
+    }
+
+    /// Calls `f` with every simple path in the reduced graph.
+    fn for_each_path(adjacency: &[Vec<usize>], path: &mut Vec<usize>, f: &mut impl FnMut(&[usize])) {
+        f(path);
+        let last = *path.last().unwrap();
+        for &a in &adjacency[last] {
+            if !path.contains(&a) {
+                path.push(a);
+                for_each_path(adjacency, path, f);
+                path.pop();
+            }
+        }
+    }
+
+    #[test]
+    fn runs_are_contracted_and_surplus_pendants_trimmed() {
+        let graph = test_graph();
+        let reduced = graph.reduce();
+        let word = |v: usize| graph.word(v);
+
+        // The run between the hubs may go either way, but pendants start at
+        // the end they are attached by.
+        let mut runs: Vec<Vec<&str>> = reduced
+            .vertices
+            .iter()
+            .map(|v| v.run.iter().map(|&v| word(v)).collect())
+            .collect();
+        for run in &mut runs {
+            if run[0] == "z" {
+                run.reverse();
+            }
+        }
+        runs.sort_unstable();
+        assert_eq!(
+            runs,
+            [
+                vec!["a"], vec!["b"], vec!["c"],
+                vec!["m", "n"], vec!["p"], vec!["q"], vec!["r"], vec!["s"],
+                vec!["t", "u", "v"], vec!["x", "y", "z"],
+            ]
+        );
+
+        assert_eq!(reduced.trimmed.len(), 1);
+        assert_eq!(reduced.trimmed[0].run, [graph.get_index_for_word("l")]);
+        assert_eq!(reduced.vertices[reduced.trimmed[0].attached_to].run, [graph.get_index_for_word("p")]);
+    }
+
+    #[test]
+    fn expanded_paths_are_paths_in_the_original_graph() {
+        let graph = test_graph();
+        let reduced = graph.reduce();
+        let adjacency: Vec<Vec<usize>> = reduced.vertices.iter().map(|v| v.adjacency_list.clone()).collect();
+        let mut num_paths = 0;
+
+        for start in 0..reduced.size() {
+            for_each_path(&adjacency, &mut vec![start], &mut |path| {
+                let expanded = reduced.expand_path(path);
+                num_paths += 1;
+
+                let weight: usize = path.iter().map(|&r| reduced.vertices[r].weight()).sum();
+                assert_eq!(expanded.len(), weight);
+
+                for pair in expanded.windows(2) {
+                    assert!(graph.is_adjacent(pair[0], pair[1]), "{:?}", expanded);
+                }
+                let mut visited = expanded.clone();
+                visited.sort_unstable();
+                visited.dedup();
+                assert_eq!(visited.len(), expanded.len());
+
+                // Each vertex comes from the reduced vertex which stands for it.
+                let mut expanded = expanded.iter();
+                for &r in path {
+                    for _ in 0..reduced.vertices[r].weight() {
+                        let v = *expanded.next().unwrap();
+                        assert!(matches!(reduced.locations[v], VertexLocation::Reduced { vertex, .. } if vertex == r));
+                    }
+                }
+            });
+        }
+
+        assert!(num_paths > reduced.size());
+    }
+}
@@ -49,6 +49,23 @@ impl RelativeDirectories {
         pb
     }
 
+    /// Returns the name of the binary file holding the graph of all the words
+    /// of a particular word length, which is much faster to load than the
+    /// 'all adjacencies' file.
+    pub fn all_graph_file(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push(format!("all_graph_{:02}.bin", word_length));
+        pb
+    }
+
+    /// Returns the name of the binary file holding the graph of the largest
+    /// component, which is much faster to load than its adjacency file.
+    pub fn largest_component_graph_file(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push(format!("largest_component_graph_{:02}.bin", word_length));
+        pb
+    }
+
     /// Returns the name of the file which will hold word length statistics
     /// computed from the graph.
     pub fn word_stats_file(&self) -> PathBuf {
@@ -63,4 +80,67 @@ impl RelativeDirectories {
         pb.push(format!("chains_{:02}", word_length));
         pb
     }
+
+    /// Returns the name of the file which lists all the chains as long as the
+    /// longest chain found for a specified word length.
+    pub fn all_longest_chains_file(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push(format!("all_longest_chains_{:02}.txt", word_length));
+        pb
+    }
+
+    /// Returns the name of the file to which progress reports are appended
+    /// while the chains of a specified word length are calculated.
+    pub fn progress_file(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push(format!("progress_{:02}.csv", word_length));
+        pb
+    }
+
+    /// Returns the name of the directory which holds the proofs that chains
+    /// of a specified word length are the longest.
+    pub fn proofs_directory(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push(format!("proofs_{:02}", word_length));
+        pb
+    }
+
+    /// Returns the name of the file which holds the longest chain found
+    /// for a specified word length.
+    pub fn longest_path_file(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.chains_directory(word_length);
+        pb.push("00_longest_path.txt");
+        pb
+    }
+
+    /// Returns the name of the file which summarises the longest chains
+    /// found for every word length.
+    pub fn longest_chains_file(&self) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push("longest_chains.csv");
+        pb
+    }
+
+    /// Returns the name of the 'loops' directory for a specified word length.
+    pub fn loops_directory(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push(format!("loops_{:02}", word_length));
+        pb
+    }
+
+    /// Returns the name of the file which holds the longest loop found
+    /// for a specified word length.
+    pub fn longest_loop_file(&self, word_length: usize) -> PathBuf {
+        let mut pb = self.loops_directory(word_length);
+        pb.push("00_longest_loop.txt");
+        pb
+    }
+
+    /// Returns the name of the file which summarises the longest loops
+    /// found for every word length.
+    pub fn longest_loops_file(&self) -> PathBuf {
+        let mut pb = self.output_directory();
+        pb.push("longest_loops.csv");
+        pb
+    }
 }
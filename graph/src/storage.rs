@@ -0,0 +1,57 @@
+use std::mem::{align_of, size_of};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+/// One of the arrays a `Graph` is made of. It is either built in memory or
+/// borrowed straight from a memory-mapped binary graph file, which it keeps
+/// mapped for as long as it is needed.
+#[derive(Debug)]
+pub(crate) enum Array<T: Element> {
+    Owned(Vec<T>),
+    /// `len` elements starting `start` bytes into the mapping.
+    Mapped { map: Arc<Mmap>, start: usize, len: usize },
+}
+
+/// The types that an `Array` can borrow from a mapped file.
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value of the type.
+pub(crate) unsafe trait Element: Copy {}
+
+unsafe impl Element for u8 {}
+unsafe impl Element for u32 {}
+
+impl<T: Element> Array<T> {
+    /// Borrows `len` elements starting `start` bytes into a mapping.
+    ///
+    /// Panics if they run past the end of the mapping or are not aligned.
+    pub(crate) fn mapped(map: &Arc<Mmap>, start: usize, len: usize) -> Self {
+        assert!(start + len * size_of::<T>() <= map.len(), "The array runs past the end of the file");
+        assert_eq!((map.as_ptr() as usize + start) % align_of::<T>(), 0, "The array is not aligned");
+
+        Array::Mapped {
+            map: Arc::clone(map),
+            start,
+            len,
+        }
+    }
+}
+
+impl<T: Element> Deref for Array<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Array::Owned(elements) => elements,
+            // Safety: `mapped` checked that the elements are within the
+            // mapping and aligned, any bit pattern is a valid `T`, and the
+            // mapping lives as long as `self` does.
+            Array::Mapped { map, start, len } => unsafe {
+                std::slice::from_raw_parts(map.as_ptr().add(*start).cast::<T>(), *len)
+            },
+        }
+    }
+}
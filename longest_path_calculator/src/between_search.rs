@@ -0,0 +1,111 @@
+use std::collections::{HashMap, VecDeque};
+
+use graph::Graph;
+
+use crate::search;
+
+/// Calculates the longest simple path from `start_idx` to `end_idx`, or returns
+/// an empty path if they are not connected.
+///
+/// A simple path can never come back to a cut vertex it has already used, so
+/// the path from `start_idx` to `end_idx` passes through exactly the blocks on
+/// the path between them in the block-cut tree, entering and leaving each one
+/// at fixed vertices. So the longest path is made of the longest path across
+/// each of those blocks, and no other block has to be searched at all.
+pub fn longest_path_between(graph: &Graph, start_idx: usize, end_idx: usize) -> Vec<usize> {
+    if start_idx == end_idx {
+        return vec![start_idx];
+    }
+
+    let tree = graph.block_cut_tree();
+
+    // Breadth-first search of the block-cut tree, going from a vertex to each
+    // of its blocks and from a block to each of its cut vertices. `crossings`
+    // records the block used to get to each vertex, and the vertex it was
+    // entered by.
+    let mut crossings: HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(start_idx);
+
+    while let Some(v) = queue.pop_front() {
+        if v == end_idx {
+            break;
+        }
+
+        for &block in &tree.vertex_blocks[v] {
+            let exits = tree.blocks[block]
+                .iter()
+                .copied()
+                .filter(|&u| u == end_idx || tree.is_cut_vertex(u));
+
+            for u in exits {
+                if u != start_idx && !crossings.contains_key(&u) {
+                    crossings.insert(u, (block, v));
+                    queue.push_back(u);
+                }
+            }
+        }
+    }
+
+    if !crossings.contains_key(&end_idx) {
+        return Vec::new();
+    }
+
+    // Walk back from the end to find the blocks in order.
+    let mut legs = Vec::new();
+    let mut v = end_idx;
+    while v != start_idx {
+        let (block, entry) = crossings[&v];
+        legs.push((block, entry, v));
+        v = entry;
+    }
+    legs.reverse();
+
+    let mut path = vec![start_idx];
+    for (block, entry, exit) in legs {
+        let vertices = &tree.blocks[block];
+        let position = |v: usize| vertices.iter().position(|&u| u == v).unwrap();
+        let csr = graph.subgraph(vertices).to_csr();
+
+        let leg = search::longest_path_to(&csr, position(entry), position(exit));
+        path.extend(leg[1..].iter().map(|&v| vertices[v]));
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_graphs::{all_paths_from, assert_is_path_from, random_graph};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn longest_path_between_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(14);
+
+        for _ in 0..100 {
+            let graph = random_graph(&mut rng);
+
+            for start_idx in 0..graph.size() {
+                let paths = all_paths_from(&graph, start_idx);
+
+                for end_idx in 0..graph.size() {
+                    let longest = paths
+                        .iter()
+                        .filter(|path| path.last() == Some(&end_idx))
+                        .map(|path| path.len())
+                        .max()
+                        .unwrap_or(0);
+
+                    let path = longest_path_between(&graph, start_idx, end_idx);
+                    assert_eq!(path.len(), longest, "From {:?} to {:?}", graph.word(start_idx), graph.word(end_idx));
+                    if !path.is_empty() {
+                        assert_is_path_from(&graph, &path, start_idx);
+                        assert_eq!(path.last(), Some(&end_idx));
+                    }
+                }
+            }
+        }
+    }
+}
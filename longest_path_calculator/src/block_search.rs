@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use graph::{BlockCutTree, Graph};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::search::{self, Incumbent};
+
+/// A block, and the cut vertex at which a path enters it.
+type Entry = (usize, usize);
+
+/// Calculates longest paths by decomposing the graph into its block-cut tree.
+///
+/// A simple path starting at `s` passes through a sequence of blocks, entering
+/// each one at a cut vertex and leaving it at a different cut vertex, because
+/// it can never come back to a cut vertex it has already used. So the longest
+/// path from `s` is made of a longest path inside the first block, continued at
+/// its last vertex by the best path through whichever neighbouring block gives
+/// the most extra vertices, and so on down the tree. Only the blocks have to be
+/// searched exhaustively, and the long tendrils hanging off the core of a word
+/// graph are mostly made of tiny blocks.
+///
+/// The best path through a block from a given entry vertex does not depend on
+/// where the overall path started, so the results are kept and shared between
+/// start words.
+pub struct BlockSolver<'a> {
+    graph: &'a Graph,
+    tree: BlockCutTree,
+    /// Maps each entry calculated so far to the longest path that starts at
+    /// the entry vertex and only uses that block and the blocks beyond it.
+    continuations: Mutex<HashMap<Entry, Arc<Vec<usize>>>>,
+    /// Where to checkpoint the search of each block, if anywhere.
+    checkpoint_directory: Option<PathBuf>,
+}
+
+impl<'a> BlockSolver<'a> {
+    pub fn new(graph: &'a Graph, checkpoint_directory: Option<PathBuf>) -> Self {
+        Self {
+            graph,
+            tree: graph.block_cut_tree(),
+            continuations: Mutex::new(HashMap::new()),
+            checkpoint_directory,
+        }
+    }
+
+    /// Calculates everything that the searches from `start_indices` will need
+    /// from the other blocks, so that each of those paths is only calculated
+    /// once however many start words share it. Without this each search
+    /// calculates what it needs itself, and searches running at the same time
+    /// may calculate the same path twice.
+    pub fn prepare(&self, start_indices: &[usize]) {
+        let entries = start_indices
+            .iter()
+            .flat_map(|&start_idx| self.entries_needed_from(start_idx))
+            .collect();
+        self.calculate_continuations(entries);
+    }
+
+    /// Returns the longest simple path starting at `start_idx`. If `incumbent`
+    /// is given then, as with `search::longest_path_from`, the result may be
+    /// shorter than it.
+    ///
+    /// The incumbent only prunes the search of the block that `start_idx` is
+    /// in. The paths through the other blocks are shared with other start
+    /// words, which may need more of them, so they are always calculated in
+    /// full.
+    pub fn longest_path_from(&self, start_idx: usize, incumbent: Option<&Incumbent>) -> Vec<usize> {
+        self.calculate_continuations(self.entries_needed_from(start_idx));
+
+        // A path from a cut vertex is one of the paths already calculated.
+        // Any other vertex is in only one block, and the path from it is not
+        // shared with any other start word so it is not kept.
+        let best = if self.tree.is_cut_vertex(start_idx) {
+            let continuations = self.continuations.lock().unwrap();
+            self.tree.vertex_blocks[start_idx]
+                .iter()
+                .map(|&block| continuations[&(block, start_idx)].as_ref().clone())
+                .max_by_key(|path| path.len())
+        } else {
+            self.tree.vertex_blocks[start_idx]
+                .first()
+                .map(|&block| self.calculate_path_through_block(block, start_idx, incumbent))
+        };
+
+        let path = best.unwrap_or_else(|| vec![start_idx]);
+        if let Some(incumbent) = incumbent {
+            incumbent.offer(path.len());
+        }
+        path
+    }
+
+    /// Returns the entries whose paths the search from `start_idx` uses
+    /// directly.
+    fn entries_needed_from(&self, start_idx: usize) -> Vec<Entry> {
+        let blocks = self.tree.vertex_blocks[start_idx].iter();
+        if self.tree.is_cut_vertex(start_idx) {
+            blocks.map(|&block| (block, start_idx)).collect()
+        } else {
+            blocks.flat_map(|&block| self.onward_entries((block, start_idx))).collect()
+        }
+    }
+
+    /// Returns the entries at which a path entering `block` at `entry` can
+    /// leave it, i.e. each of its other cut vertices, entering any of the
+    /// other blocks which that belongs to.
+    fn onward_entries(&self, (block, entry): Entry) -> impl Iterator<Item = Entry> + '_ {
+        self.tree
+            .cut_vertices(block)
+            .filter(move |&c| c != entry)
+            .flat_map(move |c| {
+                self.tree.vertex_blocks[c]
+                    .iter()
+                    .filter(move |&&b| b != block)
+                    .map(move |&b| (b, c))
+            })
+    }
+
+    /// Calculates the paths for `entries`, and for all the entries beyond
+    /// them, which are not known already. The path for an entry only depends
+    /// on those for its onward entries, which are further from it down the
+    /// block-cut tree, so they are calculated a level at a time, starting with
+    /// the blocks at the far ends of the tree. The entries on each level are
+    /// calculated in parallel.
+    ///
+    /// Nothing here waits for a path being calculated by another thread, as
+    /// while a rayon thread waits it may pick up another search which needs
+    /// the very path that it is in the middle of calculating.
+    fn calculate_continuations(&self, entries: Vec<Entry>) {
+        // The levels are found by a depth-first walk with an explicit stack,
+        // as along a run of bridges the tree is as deep as the run is long.
+        // Each entry is pushed once to visit its onward entries, then again to
+        // be given its level once they all have theirs.
+        let mut levels: HashMap<Entry, usize> = HashMap::new();
+        {
+            let known = self.continuations.lock().unwrap();
+            let mut stack: Vec<(Entry, bool)> = entries.into_iter().map(|entry| (entry, false)).collect();
+
+            while let Some((entry, visited)) = stack.pop() {
+                if visited {
+                    let level = self
+                        .onward_entries(entry)
+                        .filter_map(|onward| levels.get(&onward))
+                        .map(|level| level + 1)
+                        .max()
+                        .unwrap_or(0);
+                    levels.insert(entry, level);
+                } else if !known.contains_key(&entry) && !levels.contains_key(&entry) {
+                    stack.push((entry, true));
+                    stack.extend(self.onward_entries(entry).map(|onward| (onward, false)));
+                }
+            }
+        }
+
+        let num_levels = levels.values().max().map_or(0, |level| level + 1);
+        let mut entries_by_level: Vec<Vec<Entry>> = vec![Vec::new(); num_levels];
+        for (entry, level) in levels {
+            entries_by_level[level].push(entry);
+        }
+
+        for mut entries in entries_by_level {
+            entries.sort_unstable();
+            let paths: Vec<(Entry, Arc<Vec<usize>>)> = entries
+                .par_iter()
+                .map(|&(block, entry)| ((block, entry), Arc::new(self.calculate_path_through_block(block, entry, None))))
+                .collect();
+            self.continuations.lock().unwrap().extend(paths);
+        }
+    }
+
+    /// Calculates the longest path that starts at `entry`, continues inside
+    /// `block` and then possibly leaves it through one of its other cut
+    /// vertices. The paths for the onward entries must already be known. If
+    /// `incumbent` is given the search inside the block is pruned by it.
+    fn calculate_path_through_block(
+        &self,
+        block: usize,
+        entry: usize,
+        incumbent: Option<&Incumbent>,
+    ) -> Vec<usize> {
+        // The best continuation beyond each of the block's other cut vertices.
+        let mut continuations: HashMap<usize, Arc<Vec<usize>>> = HashMap::new();
+        {
+            let known = self.continuations.lock().unwrap();
+            for (onward_block, c) in self.onward_entries((block, entry)) {
+                let path = &known[&(onward_block, c)];
+                let best = continuations.entry(c).or_insert_with(|| Arc::clone(path));
+                if path.len() > best.len() {
+                    *best = Arc::clone(path);
+                }
+            }
+        }
+
+        let block_vertices = &self.tree.blocks[block];
+        let path_in_block = if block_vertices.len() == 2 {
+            // A bridge, there is only one way to cross it.
+            let other = if block_vertices[0] == entry {
+                block_vertices[1]
+            } else {
+                block_vertices[0]
+            };
+            vec![entry, other]
+        } else {
+            self.longest_path_in_block(block, entry, &continuations, incumbent)
+        };
+
+        let mut path = path_in_block;
+        if let Some(continuation) = path.last().and_then(|last| continuations.get(last)) {
+            // The continuation starts with the cut vertex we are already on.
+            path.extend_from_slice(&continuation[1..]);
+        }
+
+        path
+    }
+
+    /// Searches exhaustively inside a single block for the path from `entry`
+    /// which is longest once the continuation from its last vertex is added on.
+    fn longest_path_in_block(
+        &self,
+        block: usize,
+        entry: usize,
+        continuations: &HashMap<usize, Arc<Vec<usize>>>,
+        incumbent: Option<&Incumbent>,
+    ) -> Vec<usize> {
+        let block_vertices = &self.tree.blocks[block];
+        let subgraph = self.graph.subgraph(block_vertices).to_csr();
+
+        let end_bonus: Vec<usize> = block_vertices
+            .iter()
+            .map(|v| continuations.get(v).map_or(0, |path| path.len() - 1))
+            .collect();
+
+        let local_entry = block_vertices
+            .iter()
+            .position(|&v| v == entry)
+            .expect("The entry vertex should be in the block");
+
+        let checkpoint_file = self.checkpoint_directory.as_ref().map(|dir| {
+            dir.join(format!(
+                "{}.block{}.checkpoint",
+                self.graph.word(entry), block
+            ))
+        });
+
+        let path = search::longest_path_with_end_bonus(
+            &subgraph,
+            local_entry,
+            &end_bonus,
+            usize::MAX,
+            incumbent,
+            checkpoint_file.as_deref(),
+        );
+
+        path.into_iter()
+            .map(|local_idx| block_vertices[local_idx])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graph::GraphBuilder;
+
+    /// Returns a distinct five-letter word for each number.
+    fn word(n: usize) -> String {
+        (0..5).map(|i| (b'a' + (n / 26usize.pow(i) % 26) as u8) as char).collect()
+    }
+
+    #[test]
+    fn a_long_run_of_bridges_does_not_overflow_the_stack() {
+        const LENGTH: usize = 20_000;
+
+        let mut builder = GraphBuilder::new();
+        for n in 1..LENGTH {
+            builder.add_edge(&word(n - 1), &word(n)).unwrap();
+        }
+        let graph = builder.build();
+        let solver = BlockSolver::new(&graph, None);
+
+        let path = solver.longest_path_from(0, None);
+        assert_eq!(path, (0..LENGTH).collect::<Vec<_>>());
+
+        // From the middle the path runs to the further end.
+        let path = solver.longest_path_from(LENGTH / 3, None);
+        assert_eq!(path.len(), LENGTH - LENGTH / 3);
+        assert_eq!(path.last(), Some(&(LENGTH - 1)));
+    }
+}
@@ -0,0 +1,413 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use graph::{atomic_write, checksum, RelativeDirectories};
+
+/// The start of the header line of every chain file. Change the version
+/// if the format changes.
+const HEADER_PREFIX: &str = "# word_chains chain v1";
+
+/// How a chain was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// An exact search which ran to completion, so there is no longer chain
+    /// from the start word.
+    Exhaustive,
+    /// An exact search which stopped as soon as its chain reached the upper
+    /// bound, so again there is no longer chain.
+    Bounded,
+    /// A heuristic search, so there may be a longer chain.
+    Heuristic,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Exhaustive => write!(f, "exhaustive"),
+            Method::Bounded => write!(f, "bounded"),
+            Method::Heuristic => write!(f, "heuristic"),
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exhaustive" => Ok(Method::Exhaustive),
+            "bounded" => Ok(Method::Bounded),
+            "heuristic" => Ok(Method::Heuristic),
+            _ => Err(format!("Unknown method '{}'", s)),
+        }
+    }
+}
+
+/// Why a chain is known to be the longest in the whole graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Proof {
+    /// The chain is as long as the upper bound.
+    UpperBound,
+    /// The chain from every start word was found by an exact search, and this
+    /// is the longest of them.
+    EveryStartWord,
+    /// Every component which could hold a longer chain was solved exactly,
+    /// and this is the longest of their chains.
+    EveryComponent,
+    /// A single exact search of the whole graph found it.
+    WholeGraph,
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Proof::UpperBound => write!(f, "upper_bound"),
+            Proof::EveryStartWord => write!(f, "every_start_word"),
+            Proof::EveryComponent => write!(f, "every_component"),
+            Proof::WholeGraph => write!(f, "whole_graph"),
+        }
+    }
+}
+
+impl FromStr for Proof {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upper_bound" => Ok(Proof::UpperBound),
+            "every_start_word" => Ok(Proof::EveryStartWord),
+            "every_component" => Ok(Proof::EveryComponent),
+            "whole_graph" => Ok(Proof::WholeGraph),
+            _ => Err(format!("Unknown proof '{}'", s)),
+        }
+    }
+}
+
+/// What was known about a chain when it was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Provenance {
+    pub method: Method,
+    /// The best upper bound known on the length of the longest chain in the
+    /// whole graph, such as `largest_component_upper_bound`.
+    pub upper_bound: usize,
+    /// Why the chain is the longest in the whole graph, if it is known to be.
+    pub proof: Option<Proof>,
+}
+
+impl Provenance {
+    /// Describes a chain of `length` words found by an exact search, or by
+    /// a heuristic one if `exact` is false.
+    pub fn new(exact: bool, length: usize, upper_bound: usize) -> Self {
+        let method = if !exact {
+            Method::Heuristic
+        } else if length >= upper_bound {
+            Method::Bounded
+        } else {
+            Method::Exhaustive
+        };
+
+        Self {
+            method,
+            upper_bound,
+            proof: if length >= upper_bound { Some(Proof::UpperBound) } else { None },
+        }
+    }
+
+    /// Records that the chain is the longest in the whole graph because of
+    /// `proof`, unless it already meets the upper bound, which is proof enough.
+    pub fn proven_by(self, proof: Proof) -> Self {
+        Self {
+            proof: self.proof.or(Some(proof)),
+            ..self
+        }
+    }
+}
+
+/// The contents of a chain file.
+#[derive(Debug, Clone)]
+pub struct ChainFile {
+    pub chain: Vec<String>,
+    /// None for files written before provenance was recorded.
+    pub provenance: Option<Provenance>,
+}
+
+impl ChainFile {
+    /// Returns true if the chain is known to be the longest from its start
+    /// word, because an exact search from the word found it, or because no
+    /// chain at all is longer.
+    pub fn is_longest_from_start_word(&self) -> bool {
+        self.is_proven_longest() || self.provenance.is_some_and(|p| p.method != Method::Heuristic)
+    }
+
+    /// Returns true if the chain is known to be the longest in the whole
+    /// graph, in which case `Provenance::proof` says why.
+    pub fn is_proven_longest(&self) -> bool {
+        self.provenance.is_some_and(|p| p.proof.is_some())
+    }
+}
+
+/// The reasons a chain file can be rejected.
+#[derive(Debug)]
+pub enum ChainFileError {
+    Io(io::Error),
+    /// The file does not start with a header. Files written by older versions
+    /// of the program have no header, and cannot be checked for truncation.
+    MissingHeader,
+    BadHeader(String),
+    /// The number of words does not match the header, so the file is
+    /// probably truncated.
+    WrongLength { expected: usize, actual: usize },
+    WrongChecksum,
+}
+
+impl fmt::Display for ChainFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainFileError::Io(e) => write!(f, "{}", e),
+            ChainFileError::MissingHeader => write!(f, "the file has no header"),
+            ChainFileError::BadHeader(header) => write!(f, "the header '{}' is invalid", header),
+            ChainFileError::WrongLength { expected, actual } => write!(
+                f,
+                "the header says there are {} words but there are {}",
+                expected, actual
+            ),
+            ChainFileError::WrongChecksum => write!(f, "the checksum does not match"),
+        }
+    }
+}
+
+impl From<io::Error> for ChainFileError {
+    fn from(e: io::Error) -> Self {
+        ChainFileError::Io(e)
+    }
+}
+
+/// Writes a chain file. The file consists of a header line recording the
+/// length of the chain, a checksum of the words and how the chain was found,
+/// followed by the words on a single line separated by spaces. It is written
+/// with `atomic_write`.
+///
+/// The header says whether the chain is the longest from its start word
+/// (`exhaustive`) and whether it is the longest in the whole graph (`proven`),
+/// and if it is, why (`proof`).
+pub fn write_chain_file(filename: &Path, chain_file: &ChainFile) -> io::Result<()> {
+    let words = chain_file.chain.join(" ");
+
+    let mut contents = Vec::new();
+    write!(
+        contents,
+        "{} length={} checksum={:016x}",
+        HEADER_PREFIX,
+        chain_file.chain.len(),
+        checksum(words.as_bytes())
+    )?;
+    if let Some(provenance) = &chain_file.provenance {
+        write!(
+            contents,
+            " method={} upper_bound={} exhaustive={} proven={}",
+            provenance.method,
+            provenance.upper_bound,
+            yes_or_no(chain_file.is_longest_from_start_word()),
+            yes_or_no(chain_file.is_proven_longest())
+        )?;
+        if let Some(proof) = provenance.proof {
+            write!(contents, " proof={}", proof)?;
+        }
+    }
+    writeln!(contents)?;
+    writeln!(contents, "{}", words)?;
+
+    atomic_write(filename, &contents)
+}
+
+fn yes_or_no(b: bool) -> &'static str {
+    if b {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Writes a file listing many chains of the same length, one per line. The
+/// header line records their length, how many such chains there are in total,
+/// and how many of them are listed, which is fewer if the list was capped.
+/// It is written with `atomic_write`.
+pub fn write_chain_list_file(filename: &Path, count: u64, chains: &[Vec<String>]) -> io::Result<()> {
+    let mut contents = Vec::new();
+    writeln!(
+        contents,
+        "# word_chains chain list v1 length={} count={} listed={}",
+        chains.first().map_or(0, Vec::len),
+        count,
+        chains.len()
+    )?;
+    for chain in chains {
+        writeln!(contents, "{}", chain.join(" "))?;
+    }
+
+    atomic_write(filename, &contents)
+}
+
+/// Reads a chain file, checking it against its header.
+pub fn read_chain_file(filename: &Path) -> Result<ChainFile, ChainFileError> {
+    let f = fs::File::open(filename)?;
+    let mut lines = io::BufReader::new(f).lines();
+
+    let header = match lines.next() {
+        Some(header) => header?,
+        None => return Err(ChainFileError::MissingHeader),
+    };
+
+    let fields = match header.strip_prefix(HEADER_PREFIX) {
+        Some(fields) => fields,
+        None => return Err(ChainFileError::MissingHeader),
+    };
+
+    let mut length = None;
+    let mut expected_checksum = None;
+    let mut method = None;
+    let mut upper_bound = None;
+    let mut proof = None;
+    for field in fields.split_whitespace() {
+        match field.split_once('=') {
+            Some(("length", value)) => length = value.parse::<usize>().ok(),
+            Some(("checksum", value)) => expected_checksum = u64::from_str_radix(value, 16).ok(),
+            Some(("method", value)) => method = Some(value.parse::<Method>().ok()),
+            Some(("upper_bound", value)) => upper_bound = Some(value.parse::<usize>().ok()),
+            Some(("proof", value)) => proof = Some(value.parse::<Proof>().ok()),
+            _ => {}
+        }
+    }
+
+    let (length, expected_checksum) = match (length, expected_checksum) {
+        (Some(length), Some(expected_checksum)) => (length, expected_checksum),
+        _ => return Err(ChainFileError::BadHeader(header)),
+    };
+
+    // Files written before provenance was recorded have neither field, and
+    // those written before proofs were recorded only had the upper bound.
+    let proof = match proof {
+        Some(Some(proof)) => Some(proof),
+        Some(None) => return Err(ChainFileError::BadHeader(header)),
+        None => None,
+    };
+    let provenance = match (method, upper_bound) {
+        (Some(Some(method)), Some(Some(upper_bound))) => Some(Provenance {
+            method,
+            upper_bound,
+            proof: proof.or(if length >= upper_bound { Some(Proof::UpperBound) } else { None }),
+        }),
+        (None, None) if proof.is_none() => None,
+        _ => return Err(ChainFileError::BadHeader(header)),
+    };
+
+    let words = match lines.next() {
+        Some(words) => words?,
+        None => String::new(),
+    };
+
+    let chain: Vec<String> = words.split(' ').filter(|w| !w.is_empty()).map(|w| w.to_string()).collect();
+    if chain.len() != length {
+        return Err(ChainFileError::WrongLength {
+            expected: length,
+            actual: chain.len(),
+        });
+    }
+
+    if checksum(words.as_bytes()) != expected_checksum {
+        return Err(ChainFileError::WrongChecksum);
+    }
+
+    Ok(ChainFile { chain, provenance })
+}
+
+/// Returns true if `filename` is the chain file for a start word, as opposed
+/// to the longest path file, a checkpoint, or a file being written.
+pub fn is_chain_file(dirs: &RelativeDirectories, word_length: usize, filename: &Path) -> bool {
+    filename.extension().is_some_and(|ext| ext == "txt")
+        && filename != dirs.longest_path_file(word_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn test_chain_file() -> ChainFile {
+        ChainFile {
+            chain: ["cold", "cord", "card", "ward", "warm"].iter().map(|w| w.to_string()).collect(),
+            provenance: Some(Provenance::new(true, 5, 7).proven_by(Proof::EveryStartWord)),
+        }
+    }
+
+    fn test_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("chain_file_{}_{}.txt", std::process::id(), name))
+    }
+
+    #[test]
+    fn chain_file_survives_a_round_trip() {
+        let filename = test_file("round_trip");
+
+        let with_provenance = test_chain_file();
+        let without_provenance = ChainFile {
+            provenance: None,
+            ..test_chain_file()
+        };
+        let bounded = ChainFile {
+            provenance: Some(Provenance::new(false, 5, 5)),
+            ..test_chain_file()
+        };
+
+        for chain_file in [with_provenance, without_provenance, bounded] {
+            write_chain_file(&filename, &chain_file).unwrap();
+            let read = read_chain_file(&filename).unwrap();
+            assert_eq!(read.chain, chain_file.chain);
+            assert_eq!(read.provenance, chain_file.provenance);
+        }
+
+        let header = fs::read_to_string(&filename).unwrap().lines().next().unwrap().to_string();
+        assert!(header.ends_with(" method=heuristic upper_bound=5 exhaustive=yes proven=yes proof=upper_bound"));
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let filename = test_file("truncated");
+        write_chain_file(&filename, &test_chain_file()).unwrap();
+        let contents = fs::read(&filename).unwrap();
+
+        // Losing just the final newline loses nothing.
+        for length in 0..contents.len() - 1 {
+            fs::write(&filename, &contents[..length]).unwrap();
+            assert!(read_chain_file(&filename).is_err(), "Read the first {} bytes", length);
+        }
+
+        fs::write(&filename, &contents[..contents.len() - 1]).unwrap();
+        assert_eq!(read_chain_file(&filename).unwrap().chain, test_chain_file().chain);
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn changed_words_are_rejected() {
+        let filename = test_file("changed");
+        write_chain_file(&filename, &test_chain_file()).unwrap();
+        let contents = fs::read_to_string(&filename).unwrap();
+
+        fs::write(&filename, contents.replace("card", "curd")).unwrap();
+        assert!(matches!(read_chain_file(&filename), Err(ChainFileError::WrongChecksum)));
+
+        fs::write(&filename, contents.replace(" warm", "")).unwrap();
+        assert!(matches!(
+            read_chain_file(&filename),
+            Err(ChainFileError::WrongLength { expected: 5, actual: 4 })
+        ));
+
+        fs::write(&filename, contents.replace("proof=every_start_word", "proof=guesswork")).unwrap();
+        assert!(matches!(read_chain_file(&filename), Err(ChainFileError::BadHeader(_))));
+
+        fs::remove_file(&filename).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use graph::atomic_write;
+
+/// The first line of every checkpoint file. Change the version if the format changes.
+const HEADER: &str = "# word_chains search checkpoint v1";
+
+/// The fields which every checkpoint file has, one per line after the header.
+const FIELDS: [&str; 7] = ["graph_size", "num_adjacencies", "start", "best_score", "best", "path", "cursors"];
+
+/// The state of an exhaustive search, saved periodically so that a long
+/// search can be resumed exactly where it stopped if the process is killed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The number of vertices in the graph being searched, and the total
+    /// length of its adjacency lists. These are used to check that the
+    /// checkpoint belongs to the same graph.
+    pub graph_size: usize,
+    pub num_adjacencies: usize,
+    pub start_idx: usize,
+    /// The path currently being explored.
+    pub path: Vec<usize>,
+    /// For each entry in `path`, the position in that vertex's adjacency
+    /// list of the next neighbour to try.
+    pub cursors: Vec<usize>,
+    pub best: Vec<usize>,
+    pub best_score: usize,
+}
+
+impl Checkpoint {
+    /// Saves the checkpoint with `atomic_write`, so that a crash part way
+    /// through leaves the previous checkpoint to resume from.
+    pub fn save(&self, filename: &Path) -> io::Result<()> {
+        let mut contents = Vec::new();
+        writeln!(contents, "{}", HEADER)?;
+        writeln!(contents, "graph_size {}", self.graph_size)?;
+        writeln!(contents, "num_adjacencies {}", self.num_adjacencies)?;
+        writeln!(contents, "start {}", self.start_idx)?;
+        writeln!(contents, "best_score {}", self.best_score)?;
+        writeln!(contents, "best {}", join(&self.best))?;
+        writeln!(contents, "path {}", join(&self.path))?;
+        writeln!(contents, "cursors {}", join(&self.cursors))?;
+
+        atomic_write(filename, &contents)
+    }
+
+    /// Loads a checkpoint, returning None if the file does not exist, cannot
+    /// be understood or has been cut short.
+    pub fn load(filename: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(filename).ok()?;
+
+        // A file cut short in the middle of a line could still parse, with
+        // the last number cut short too.
+        if !contents.ends_with('\n') {
+            return None;
+        }
+
+        let mut lines = contents.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+
+        let mut checkpoint = Checkpoint::default();
+        let mut keys = HashSet::new();
+        for line in lines {
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next()?;
+            if !FIELDS.contains(&key) || !keys.insert(key) {
+                return None;
+            }
+
+            let values = parts
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .map(|v| v.parse::<usize>().ok())
+                .collect::<Option<Vec<_>>>()?;
+
+            match key {
+                "graph_size" => checkpoint.graph_size = *values.first()?,
+                "num_adjacencies" => checkpoint.num_adjacencies = *values.first()?,
+                "start" => checkpoint.start_idx = *values.first()?,
+                "best_score" => checkpoint.best_score = *values.first()?,
+                "best" => checkpoint.best = values,
+                "path" => checkpoint.path = values,
+                "cursors" => checkpoint.cursors = values,
+                _ => return None,
+            }
+        }
+
+        if keys.len() != FIELDS.len()
+            || checkpoint.path.is_empty()
+            || checkpoint.path.len() != checkpoint.cursors.len()
+        {
+            return None;
+        }
+
+        Some(checkpoint)
+    }
+
+    /// Deletes a checkpoint file once the search it belongs to is complete.
+    pub fn remove(filename: &Path) {
+        // It does not matter if there was no checkpoint to remove.
+        let _ = fs::remove_file(filename);
+    }
+}
+
+fn join(values: &[usize]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_checkpoint() -> Checkpoint {
+        Checkpoint {
+            graph_size: 12,
+            num_adjacencies: 30,
+            start_idx: 4,
+            path: vec![4, 7, 11],
+            cursors: vec![2, 1, 10],
+            best: vec![4, 7, 11, 3, 0],
+            best_score: 5,
+        }
+    }
+
+    fn test_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("checkpoint_{}_{}.checkpoint", std::process::id(), name))
+    }
+
+    #[test]
+    fn checkpoint_survives_a_round_trip() {
+        let filename = test_file("round_trip");
+        test_checkpoint().save(&filename).unwrap();
+        let loaded = Checkpoint::load(&filename);
+        Checkpoint::remove(&filename);
+
+        assert_eq!(loaded, Some(test_checkpoint()));
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let filename = test_file("truncated");
+        test_checkpoint().save(&filename).unwrap();
+        let contents = fs::read(&filename).unwrap();
+
+        // However much is lost, even part of the last number, it is noticed.
+        for length in 0..contents.len() {
+            fs::write(&filename, &contents[..length]).unwrap();
+            assert_eq!(Checkpoint::load(&filename), None, "Loaded the first {} bytes", length);
+        }
+
+        Checkpoint::remove(&filename);
+    }
+
+    #[test]
+    fn mismatched_file_is_rejected() {
+        let filename = test_file("mismatched");
+        test_checkpoint().save(&filename).unwrap();
+        let contents = fs::read_to_string(&filename).unwrap();
+
+        let damaged = [
+            contents.replace(HEADER, "# word_chains search checkpoint v2"),
+            contents.replace("cursors 2 1 10", "cursors 2 1"),
+            contents.replace("best_score 5", "best_score five"),
+            contents.replace("start 4", "start 4\nstart 5"),
+            contents.replace("start 4", "finish 4"),
+        ];
+        for damaged in &damaged {
+            fs::write(&filename, damaged).unwrap();
+            assert_eq!(Checkpoint::load(&filename), None, "Loaded {:?}", damaged);
+        }
+
+        Checkpoint::remove(&filename);
+        assert_eq!(Checkpoint::load(&filename), None);
+    }
+}
@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 use graph::RelativeDirectories;
 
+use crate::chain_file::{is_chain_file, read_chain_file};
+
 #[derive(Default)]
 pub struct CompletedWords {
     completed: HashMap<usize, Vec<String>>,
@@ -16,6 +19,9 @@ pub fn create_chain_directories(dirs: &RelativeDirectories, word_lengths: &[usiz
     }
 }
 
+/// Finds the words whose chain files have already been written. Each file is
+/// checked, and any that is damaged or was not written for its start word is
+/// reported and left out, so that the word is calculated again.
 pub fn get_completed_words(dirs: &RelativeDirectories, word_lengths: &[usize]) -> CompletedWords {
     let mut completed_words = CompletedWords::default();
 
@@ -23,18 +29,32 @@ pub fn get_completed_words(dirs: &RelativeDirectories, word_lengths: &[usize]) -
         let dir = dirs.chains_directory(*word_length);
 
         if let Ok(dir) = dir.read_dir() {
-            for dir_entry in dir {
-                if let Ok(file) = dir_entry {
-                    let filename = file.path();
-                    let filename = filename.file_stem().unwrap();
-                    let filename = filename.to_string_lossy().into_owned();
-
-                    let entry = completed_words
-                        .completed
-                        .entry(*word_length)
-                        .or_insert_with(|| Vec::<String>::new());
-                    entry.push(filename);
+            for file in dir.flatten() {
+                let filename = file.path();
+
+                // Only chain files count, not the longest path file, the
+                // checkpoints of searches which are still in progress or
+                // chains which were being written when the program stopped.
+                if !is_chain_file(dirs, *word_length, &filename) {
+                    continue;
+                }
+
+                let word = filename.file_stem().unwrap();
+                let word = word.to_string_lossy().into_owned();
+
+                if let Err(reason) = check_chain_file(&filename, &word, *word_length) {
+                    println!(
+                        "Ignoring the chain file {:?} as {}, the chain will be recalculated",
+                        filename, reason
+                    );
+                    continue;
                 }
+
+                let entry = completed_words
+                    .completed
+                    .entry(*word_length)
+                    .or_insert_with(Vec::<String>::new);
+                entry.push(word);
             }
         }
     }
@@ -42,6 +62,41 @@ pub fn get_completed_words(dirs: &RelativeDirectories, word_lengths: &[usize]) -
     completed_words
 }
 
+/// Checks that a chain file is intact and holds a chain starting at `word`.
+fn check_chain_file(filename: &Path, word: &str, word_length: usize) -> Result<(), String> {
+    let chain = read_chain_file(filename).map_err(|e| e.to_string())?.chain;
+
+    if chain.first().map(String::as_str) != Some(word) {
+        return Err(format!("the chain does not start with '{}'", word));
+    }
+
+    if let Some(w) = chain.iter().find(|w| w.len() != word_length) {
+        return Err(format!("'{}' is not of length {}", w, word_length));
+    }
+
+    Ok(())
+}
+
+/// Returns the words of a given length which have an interrupted search
+/// checkpointed in their chains directory.
+pub fn get_checkpointed_words(dirs: &RelativeDirectories, word_length: usize) -> HashSet<String> {
+    let mut words = HashSet::new();
+
+    if let Ok(dir) = dirs.chains_directory(word_length).read_dir() {
+        for file in dir.flatten() {
+            let filename = file.file_name();
+            let filename = filename.to_string_lossy();
+            if filename.ends_with(".checkpoint") {
+                if let Some(word) = filename.split('.').next() {
+                    words.insert(word.to_string());
+                }
+            }
+        }
+    }
+
+    words
+}
+
 impl CompletedWords {
     /// Returns the number of words complete of the specified length.
     pub fn num_complete(&self, word_length: usize) -> usize {
@@ -61,3 +116,50 @@ impl CompletedWords {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_file::{write_chain_file, ChainFile, Provenance};
+    use std::fs;
+
+    fn write_chain(dirs: &RelativeDirectories, name: &str, chain: &[&str]) {
+        let chain_file = ChainFile {
+            chain: chain.iter().map(|w| w.to_string()).collect(),
+            provenance: Some(Provenance::new(true, chain.len(), 10)),
+        };
+        write_chain_file(&dirs.chains_directory(4).join(name), &chain_file).unwrap();
+    }
+
+    #[test]
+    fn damaged_chain_files_are_not_completed() {
+        let base = std::env::temp_dir().join(format!("completed_words_{}", std::process::id()));
+        let dirs = RelativeDirectories::new(base.join("dictionaries"));
+        create_chain_directories(&dirs, &[4]);
+        let chains = dirs.chains_directory(4);
+
+        write_chain(&dirs, "cold.txt", &["cold", "cord", "card"]);
+        write_chain(&dirs, "warm.txt", &["warm", "ward", "card"]);
+
+        // Cut short, changed, and written for another word.
+        write_chain(&dirs, "card.txt", &["card", "cord", "cold"]);
+        let contents = fs::read_to_string(chains.join("card.txt")).unwrap();
+        fs::write(chains.join("card.txt"), &contents[..contents.len() - 4]).unwrap();
+        write_chain(&dirs, "cord.txt", &["cord", "card", "ward"]);
+        let contents = fs::read_to_string(chains.join("cord.txt")).unwrap();
+        fs::write(chains.join("cord.txt"), contents.replace("ward", "word")).unwrap();
+        write_chain(&dirs, "ward.txt", &["warm", "ward"]);
+
+        // Not chain files at all.
+        write_chain(&dirs, "00_longest_path.txt", &["cold", "cord", "card"]);
+        fs::write(chains.join("worm.checkpoint"), "").unwrap();
+
+        let completed = get_completed_words(&dirs, &[4]);
+        let mut words = completed.completed_words_of_length(4).to_vec();
+        words.sort();
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(words, ["cold", "warm"]);
+        assert_eq!(completed.num_complete(4), 2);
+    }
+}
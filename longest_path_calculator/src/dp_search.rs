@@ -0,0 +1,219 @@
+use graph::Graph;
+
+use crate::search::Incumbent;
+
+/// The largest graph that `DpSolver` can handle, as it stores sets of
+/// vertices as the bits of a `u32`. Its tables grow as 2 to the power of the
+/// number of vertices, so in practice the limit is memory long before this:
+/// the `ends` table takes 4 MB for 20 vertices but 4 GB for 30, and the time
+/// grows in the same way. That is why `--dp-max-size` defaults to 20, beyond
+/// which backtracking is usually quicker anyway, while still letting it be
+/// raised as far as this on a machine with the memory to spare.
+pub const MAX_DP_SIZE: usize = 30;
+
+/// Calculates the longest paths in a small graph exactly, by dynamic
+/// programming over the subsets of its vertices in the manner of the
+/// Held-Karp algorithm. For each subset it records the vertices at which a
+/// path visiting exactly that subset can end: a path through a subset ends
+/// at `v` if a path through the rest of the subset ends at a neighbour of
+/// `v`. A path which ends at a vertex can be reversed to start there, so this
+/// one table answers the longest path from every start vertex at once, in
+/// time proportional to `2^n * n` however hard the graph is to backtrack over.
+///
+/// As it does not depend on the other solvers at all, it is also a useful
+/// cross-check for them.
+pub struct DpSolver {
+    /// The neighbours of each vertex, as a set.
+    neighbours: Vec<u32>,
+    /// For each subset of the vertices, the vertices at which a path visiting
+    /// exactly that subset can end.
+    ends: Vec<u32>,
+    /// For each vertex, the largest subset which a path ending there can
+    /// visit.
+    longest: Vec<usize>,
+}
+
+impl DpSolver {
+    pub fn new(graph: &Graph) -> Self {
+        let size = graph.size();
+        assert!(size <= MAX_DP_SIZE, "Graph is too large to solve by dynamic programming");
+
+        let neighbours: Vec<u32> = (0..size)
+            .map(|v| graph.neighbours(v).fold(0, |set, a| set | 1 << a))
+            .collect();
+
+        let mut ends = vec![0u32; 1 << size];
+        let mut longest = vec![0usize; size];
+
+        // Every subset is larger than the subsets within it, so these are
+        // always calculated first.
+        for subset in 1..ends.len() {
+            let mut subset_ends = 0;
+
+            if subset.is_power_of_two() {
+                subset_ends = subset as u32;
+            } else {
+                for v in vertices(subset as u32) {
+                    if ends[subset & !(1 << v)] & neighbours[v] != 0 {
+                        subset_ends |= 1 << v;
+                    }
+                }
+            }
+
+            ends[subset] = subset_ends;
+            for v in vertices(subset_ends) {
+                if subset.count_ones() > longest[v].count_ones() {
+                    longest[v] = subset;
+                }
+            }
+        }
+
+        Self {
+            neighbours,
+            ends,
+            longest,
+        }
+    }
+
+    /// Returns the longest path starting at `start_idx`. If `incumbent` is
+    /// given then, as with `search::longest_path_from`, the result may be
+    /// shorter than it: the length is already in the table, so the path is
+    /// only worked out if it is longer.
+    pub fn longest_path_from(&self, start_idx: usize, incumbent: Option<&Incumbent>) -> Vec<usize> {
+        let length = self.longest[start_idx].count_ones() as usize;
+        if let Some(incumbent) = incumbent {
+            if length <= incumbent.get() {
+                return vec![start_idx];
+            }
+            incumbent.offer(length);
+        }
+
+        let mut path = self.path_ending_at(self.longest[start_idx], start_idx);
+        path.reverse();
+        path
+    }
+
+    /// Returns the longest path in the graph.
+    pub fn longest_path(&self) -> Vec<usize> {
+        match (0..self.longest.len()).max_by_key(|&v| (self.longest[v].count_ones(), std::cmp::Reverse(v))) {
+            Some(end_idx) => self.path_ending_at(self.longest[end_idx], end_idx),
+            None => Vec::new(),
+        }
+    }
+
+    /// Reconstructs a path which visits exactly the vertices in `subset` and
+    /// ends at `end_idx`, working back from the end.
+    fn path_ending_at(&self, subset: usize, end_idx: usize) -> Vec<usize> {
+        let mut path = vec![end_idx];
+        let mut subset = subset & !(1 << end_idx);
+        let mut end_idx = end_idx;
+
+        while subset != 0 {
+            end_idx = (self.ends[subset] & self.neighbours[end_idx]).trailing_zeros() as usize;
+            path.push(end_idx);
+            subset &= !(1 << end_idx);
+        }
+
+        path.reverse();
+        path
+    }
+}
+
+/// Iterates over the vertices in a set.
+fn vertices(set: u32) -> impl Iterator<Item = usize> {
+    let mut rest = set;
+    std::iter::from_fn(move || {
+        if rest == 0 {
+            None
+        } else {
+            let v = rest.trailing_zeros() as usize;
+            rest &= rest - 1;
+            Some(v)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_search::BlockSolver;
+    use crate::reduced_search::ReducedSolver;
+    use crate::sat_search::SatSolver;
+    use crate::search;
+    use crate::test_graphs::{assert_is_path_from, random_graph};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn dp_agrees_with_the_other_solvers() {
+        let mut rng = StdRng::seed_from_u64(18);
+
+        for _ in 0..200 {
+            let graph = random_graph(&mut rng);
+            let csr = graph.to_csr();
+            let dp = DpSolver::new(&graph);
+            let blocks = BlockSolver::new(&graph, None);
+            let reduced = ReducedSolver::new(&graph, graph.size());
+            let sat = SatSolver::new(&graph, graph.size(), None);
+
+            let mut longest = 0;
+            for start_idx in 0..graph.size() {
+                let dp_path = dp.longest_path_from(start_idx, None);
+                assert_is_path_from(&graph, &dp_path, start_idx);
+                longest = longest.max(dp_path.len());
+
+                let paths = [
+                    search::longest_path_from(&csr, start_idx, graph.size(), None, None),
+                    blocks.longest_path_from(start_idx, None),
+                    reduced.longest_path_from(start_idx, None),
+                    sat.longest_path_from(start_idx, None),
+                ];
+                for path in &paths {
+                    assert_is_path_from(&graph, path, start_idx);
+                    assert_eq!(path.len(), dp_path.len(), "Different lengths from {:?}", graph.word(start_idx));
+                }
+            }
+
+            assert_eq!(dp.longest_path().len(), longest);
+            assert_eq!(sat.longest_path(None).len(), longest);
+        }
+    }
+
+    #[test]
+    fn incumbents_only_prune_paths_no_longer_than_them() {
+        let mut rng = StdRng::seed_from_u64(9);
+
+        for _ in 0..100 {
+            let graph = random_graph(&mut rng);
+            let dp = DpSolver::new(&graph);
+            let blocks = BlockSolver::new(&graph, None);
+
+            for start_idx in 0..graph.size() {
+                let length = dp.longest_path_from(start_idx, None).len();
+
+                // Just below the incumbent the longest path is still found.
+                let below = [Incumbent::new(length - 1), Incumbent::new(length - 1)];
+                let paths = [
+                    dp.longest_path_from(start_idx, Some(&below[0])),
+                    blocks.longest_path_from(start_idx, Some(&below[1])),
+                ];
+                for (path, incumbent) in paths.iter().zip(&below) {
+                    assert_is_path_from(&graph, path, start_idx);
+                    assert_eq!(path.len(), length);
+                    assert_eq!(incumbent.get(), length);
+                }
+
+                // At the incumbent the path found may be cut short.
+                let equal = [Incumbent::new(length), Incumbent::new(length)];
+                let paths = [
+                    dp.longest_path_from(start_idx, Some(&equal[0])),
+                    blocks.longest_path_from(start_idx, Some(&equal[1])),
+                ];
+                for (path, incumbent) in paths.iter().zip(&equal) {
+                    assert_is_path_from(&graph, path, start_idx);
+                    assert!(path.len() <= length);
+                    assert_eq!(incumbent.get(), length);
+                }
+            }
+        }
+    }
+}
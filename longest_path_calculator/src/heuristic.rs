@@ -0,0 +1,334 @@
+use std::time::{Duration, Instant};
+
+use graph::Graph;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Settings for the heuristic search.
+#[derive(Debug, Clone, Copy)]
+pub struct HeuristicOptions {
+    /// How long to spend on each start word.
+    pub time_limit: Duration,
+    /// Seed for the random number generator. Each start word gets its own
+    /// generator derived from this, so runs are repeatable.
+    pub seed: u64,
+    /// Stop as soon as a path this long is found.
+    pub upper_bound: usize,
+}
+
+/// How often, at most, `on_improvement` is called while the search is running.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The chance of ignoring the low-degree-first rule when growing a path.
+const RANDOM_STEP_PROBABILITY: f64 = 0.1;
+
+/// The number of moves without improvement after which we start afresh.
+const RESTART_AFTER: usize = 20_000;
+
+/// The annealing temperature at the start of the time limit. It falls
+/// linearly to zero by the end.
+const INITIAL_TEMPERATURE: f64 = 2.0;
+
+/// The position recorded for a vertex which must not be added to the path.
+const BLOCKED: usize = usize::MAX - 1;
+
+/// Searches for a long path starting at `start_idx` until the time limit
+/// expires. Unlike the exhaustive solvers there is no guarantee that the
+/// result is the longest path, but a good one is found quickly even in the
+/// largest graphs.
+///
+/// The search combines several methods. Paths are grown by a randomized
+/// walk which prefers the neighbour with the fewest unvisited neighbours of its
+/// own (Warnsdorff's rule, which avoids stranding vertices). They are then
+/// improved by simulated annealing, where a move either rotates the path
+/// (Pósa's transformation: if the tip is adjacent to an earlier vertex, the
+/// segment after that vertex is reversed, giving a new tip to grow from) or
+/// cuts it and regrows it from the cut. If the search stalls it restarts.
+///
+/// `on_improvement` is called with the best path found so far whenever it
+/// improves, but not more than once a second, so that an interrupted run still
+/// leaves good results behind. It is always called for the final best path.
+pub fn longest_path_from<F>(
+    graph: &Graph,
+    start_idx: usize,
+    options: &HeuristicOptions,
+    on_improvement: F,
+) -> Vec<usize>
+where
+    F: FnMut(&[usize]),
+{
+    let state = PathState::new(graph, start_idx, None, options.seed ^ start_idx as u64);
+    anneal(state, options, on_improvement)
+}
+
+/// Like `longest_path_from`, but searches for a long path from `start_idx` to
+/// `end_idx`. The path being improved avoids `end_idx`, and the candidate at
+/// each step is its longest prefix which ends next to `end_idx`, with
+/// `end_idx` added. Returns an empty path if no path to `end_idx` was found,
+/// in which case `on_improvement` is never called.
+pub fn longest_path_between<F>(
+    graph: &Graph,
+    start_idx: usize,
+    end_idx: usize,
+    options: &HeuristicOptions,
+    on_improvement: F,
+) -> Vec<usize>
+where
+    F: FnMut(&[usize]),
+{
+    if start_idx == end_idx {
+        return vec![start_idx];
+    }
+
+    let state = PathState::new(graph, start_idx, Some(end_idx), options.seed ^ start_idx as u64);
+    anneal(state, options, on_improvement)
+}
+
+/// Improves the path in `state` until the time limit expires, as described
+/// for `longest_path_from`, and returns the best candidate seen.
+fn anneal<F>(mut state: PathState, options: &HeuristicOptions, mut on_improvement: F) -> Vec<usize>
+where
+    F: FnMut(&[usize]),
+{
+    let started = Instant::now();
+    state.grow();
+
+    let mut best = Vec::new();
+    state.candidate(&mut best);
+    let mut best_reported = best.is_empty();
+    let mut last_report = started;
+    let mut moves_since_improvement = 0;
+
+    while best.len() < options.upper_bound {
+        let elapsed = started.elapsed();
+        if elapsed >= options.time_limit {
+            break;
+        }
+
+        // Do a batch of moves between checks of the clock.
+        let temperature = INITIAL_TEMPERATURE
+            * (1.0 - elapsed.as_secs_f64() / options.time_limit.as_secs_f64());
+
+        for _ in 0..64 {
+            if moves_since_improvement >= RESTART_AFTER {
+                state.restart();
+                state.grow();
+                moves_since_improvement = 0;
+            }
+
+            state.make_move(temperature);
+            moves_since_improvement += 1;
+
+            if state.candidate_len() > best.len() {
+                state.candidate(&mut best);
+                best_reported = false;
+                moves_since_improvement = 0;
+            }
+        }
+
+        if !best_reported && last_report.elapsed() >= REPORT_INTERVAL {
+            on_improvement(&best);
+            best_reported = true;
+            last_report = Instant::now();
+        }
+    }
+
+    if !best_reported {
+        on_improvement(&best);
+    }
+
+    best
+}
+
+/// The path being improved, along with fast membership lookups.
+struct PathState<'a> {
+    graph: &'a Graph,
+    rng: StdRng,
+    path: Vec<usize>,
+    /// The position of each vertex in `path`, `usize::MAX` if it is not on it,
+    /// or `BLOCKED` if it is the required end vertex.
+    position: Vec<usize>,
+    /// The vertex the result has to end at, if any.
+    end_idx: Option<usize>,
+    /// Storage for a path to restore if a move is rejected.
+    saved: Vec<usize>,
+}
+
+impl<'a> PathState<'a> {
+    fn new(graph: &'a Graph, start_idx: usize, end_idx: Option<usize>, seed: u64) -> Self {
+        let mut position = vec![usize::MAX; graph.size()];
+        position[start_idx] = 0;
+        if let Some(end_idx) = end_idx {
+            position[end_idx] = BLOCKED;
+        }
+
+        Self {
+            graph,
+            rng: StdRng::seed_from_u64(seed),
+            path: vec![start_idx],
+            position,
+            end_idx,
+            saved: Vec::with_capacity(graph.size()),
+        }
+    }
+
+    /// Returns the length of the path that the current state gives: the whole
+    /// path, or if there is a required end vertex, the longest prefix of the
+    /// path that ends next to it plus the end vertex, or 0 if there is none.
+    fn candidate_len(&self) -> usize {
+        match self.end_idx {
+            Some(end_idx) => self
+                .graph
+                .neighbours(end_idx)
+                .map(|a| self.position[a])
+                .filter(|&p| p < BLOCKED)
+                .max()
+                .map_or(0, |p| p + 2),
+            None => self.path.len(),
+        }
+    }
+
+    /// Replaces `candidate` with the path that the current state gives.
+    fn candidate(&self, candidate: &mut Vec<usize>) {
+        candidate.clear();
+
+        let len = self.candidate_len();
+        if let Some(end_idx) = self.end_idx {
+            if len > 0 {
+                candidate.extend_from_slice(&self.path[..len - 1]);
+                candidate.push(end_idx);
+            }
+        } else {
+            candidate.extend_from_slice(&self.path);
+        }
+    }
+
+    /// Goes back to a path consisting of just the start vertex.
+    fn restart(&mut self) {
+        self.truncate(1);
+    }
+
+    /// Shortens the path to `len` vertices.
+    fn truncate(&mut self, len: usize) {
+        for &v in &self.path[len..] {
+            self.position[v] = usize::MAX;
+        }
+        self.path.truncate(len);
+    }
+
+    fn push(&mut self, vertex_index: usize) {
+        self.position[vertex_index] = self.path.len();
+        self.path.push(vertex_index);
+    }
+
+    /// The number of neighbours of a vertex which are not on the path.
+    fn onward_degree(&self, vertex_index: usize) -> usize {
+        self.graph
+            .neighbours(vertex_index)
+            .filter(|&a| self.position[a] == usize::MAX)
+            .count()
+    }
+
+    /// Extends the path from its tip until it gets stuck, choosing the next
+    /// vertex by Warnsdorff's rule with occasional random steps.
+    fn grow(&mut self) {
+        let mut candidates = Vec::new();
+
+        loop {
+            let tip = *self.path.last().unwrap();
+            candidates.clear();
+            candidates.extend(
+                self.graph
+                    .neighbours(tip)
+                    .filter(|&a| self.position[a] == usize::MAX),
+            );
+
+            if candidates.is_empty() {
+                return;
+            }
+
+            let next = if self.rng.gen_bool(RANDOM_STEP_PROBABILITY) {
+                candidates[self.rng.gen_range(0..candidates.len())]
+            } else {
+                // Prefer the most constrained neighbour, but not one with no
+                // way onwards unless there is no choice, as that ends the path.
+                let mut best = Vec::new();
+                let mut best_degree = usize::MAX;
+                for &c in &candidates {
+                    let degree = match self.onward_degree(c) {
+                        0 => usize::MAX - 1,
+                        d => d,
+                    };
+                    if degree < best_degree {
+                        best_degree = degree;
+                        best.clear();
+                    }
+                    if degree == best_degree {
+                        best.push(c);
+                    }
+                }
+                best[self.rng.gen_range(0..best.len())]
+            };
+
+            self.push(next);
+        }
+    }
+
+    /// Makes one annealing move. Rotations never shorten the path so are
+    /// always accepted; a cut-and-regrow move that shortens the path is
+    /// accepted with a probability that falls as the temperature does.
+    fn make_move(&mut self, temperature: f64) {
+        if self.path.len() > 2 && self.rng.gen_bool(0.5) && self.rotate() {
+            self.grow();
+            return;
+        }
+
+        self.saved.clear();
+        self.saved.extend_from_slice(&self.path);
+        let old_len = self.path.len();
+
+        // Cuts near the tip are cheaper and usually more productive.
+        let distance_from_tip = 1 + (self.rng.gen::<f64>().powi(3) * old_len as f64) as usize;
+        let cut = old_len.saturating_sub(distance_from_tip).max(1);
+        self.truncate(cut);
+        self.grow();
+
+        let new_len = self.path.len();
+        let accept = new_len >= old_len
+            || (temperature > 0.0
+                && self.rng.gen::<f64>()
+                    < ((new_len as f64 - old_len as f64) / temperature).exp());
+
+        if !accept {
+            self.truncate(0);
+            for i in 0..self.saved.len() {
+                self.push(self.saved[i]);
+            }
+        }
+    }
+
+    /// Performs a Pósa rotation if possible. Returns false if the tip has no
+    /// neighbour on the path other than its predecessor.
+    fn rotate(&mut self) -> bool {
+        let tip_position = self.path.len() - 1;
+        let tip = self.path[tip_position];
+
+        let pivots: Vec<usize> = self
+            .graph
+            .neighbours(tip)
+            .map(|a| self.position[a])
+            .filter(|&p| p < BLOCKED && p + 1 < tip_position)
+            .collect();
+
+        if pivots.is_empty() {
+            return false;
+        }
+
+        let pivot = pivots[self.rng.gen_range(0..pivots.len())];
+        self.path[pivot + 1..].reverse();
+        for p in pivot + 1..self.path.len() {
+            self.position[self.path[p]] = p;
+        }
+
+        true
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+
+use crate::search::Incumbent;
+
+/// A chain, and the position in it of the word whose best chain it gives.
+type ChainPosition = Option<(Arc<Vec<usize>>, usize)>;
+
+/// The longest chain known so far from each word of a graph, shared between the
+/// rayon tasks calculating chains for different start words.
+///
+/// Any chain found gives a chain from every word on it, because a simple path
+/// can be walked in either direction: from the word at position `i` of a chain
+/// of length `n` we can walk back to the start, giving `i + 1` words, or on to
+/// the end, giving `n - i`. So every chain found by one task is offered to all
+/// the words on it, and the searches from those words only need to look for
+/// something longer.
+pub struct KnownChains {
+    /// For each word, the chain that the best known chain from it is taken
+    /// from, and the word's position in that chain.
+    chains: Mutex<Vec<ChainPosition>>,
+    /// The length of the best chain known from each word, including chains
+    /// found by a search from that word which is still running. The searches
+    /// use these to prune.
+    lengths: Vec<Incumbent>,
+}
+
+impl KnownChains {
+    pub fn new(graph_size: usize) -> Self {
+        Self {
+            chains: Mutex::new(vec![None; graph_size]),
+            lengths: (0..graph_size).map(|_| Incumbent::default()).collect(),
+        }
+    }
+
+    /// Records a chain, which may give a longer chain from any of the words on it.
+    pub fn offer(&self, chain: &[usize]) {
+        let n = chain.len();
+        let shared = Arc::new(chain.to_vec());
+        let mut chains = self.chains.lock().unwrap();
+
+        for (i, &v) in chain.iter().enumerate() {
+            let length = (i + 1).max(n - i);
+            if length > Self::chain_length(&chains[v]) {
+                chains[v] = Some((Arc::clone(&shared), i));
+                self.lengths[v].offer(length);
+            }
+        }
+    }
+
+    /// Returns the length of the best chain known from `vertex_index`, which
+    /// a search from it is used to prune with.
+    pub fn incumbent(&self, vertex_index: usize) -> &Incumbent {
+        &self.lengths[vertex_index]
+    }
+
+    /// Returns the best chain known from `vertex_index`, if there is one.
+    pub fn chain_from(&self, vertex_index: usize) -> Option<Vec<usize>> {
+        let chains = self.chains.lock().unwrap();
+        let (chain, i) = chains[vertex_index].as_ref()?;
+
+        if i + 1 > chain.len() - i {
+            Some(chain[..=*i].iter().rev().copied().collect())
+        } else {
+            Some(chain[*i..].to_vec())
+        }
+    }
+
+    /// Returns the longest chain known from any word.
+    pub fn longest(&self) -> Option<Vec<usize>> {
+        let vertex_index = {
+            let chains = self.chains.lock().unwrap();
+            (0..chains.len()).max_by_key(|&v| Self::chain_length(&chains[v]))?
+        };
+
+        self.chain_from(vertex_index)
+    }
+
+    fn chain_length(entry: &ChainPosition) -> usize {
+        match entry {
+            Some((chain, i)) => (i + 1).max(chain.len() - i),
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dp_search::DpSolver;
+    use crate::search;
+    use graph::Graph;
+
+    /// cat - cot - cog - dog, with cut - hut - hit hanging off the triangle
+    /// cat - cot - cut.
+    fn graph() -> Graph {
+        Graph::from_words(["cat", "cot", "cog", "dog", "cut", "hut", "hit"]).unwrap()
+    }
+
+    fn path(graph: &Graph, words: &[&str]) -> Vec<usize> {
+        words.iter().map(|word| graph.get_index_for_word(word)).collect()
+    }
+
+    #[test]
+    fn a_chain_seeds_the_search_from_its_last_word() {
+        let graph = graph();
+        let csr = graph.to_csr();
+        let dp = DpSolver::new(&graph);
+        let known_chains = KnownChains::new(graph.size());
+
+        // The longest chain from "hit" ends at "dog", so it is the longest
+        // from "dog" too, walked backwards.
+        let from_hit = path(&graph, &["hit", "hut", "cut", "cat", "cot", "cog", "dog"]);
+        assert_eq!(from_hit.len(), dp.longest_path_from(from_hit[0], None).len());
+        known_chains.offer(&from_hit);
+
+        let dog = graph.get_index_for_word("dog");
+        let incumbent = known_chains.incumbent(dog);
+        assert_eq!(incumbent.get(), from_hit.len());
+
+        // So the search from "dog" has nothing longer to find.
+        let found = search::longest_path_from(&csr, dog, graph.size(), Some(incumbent), None);
+        assert!(found.len() <= from_hit.len());
+        known_chains.offer(&found);
+
+        let mut reversed = from_hit.clone();
+        reversed.reverse();
+        assert_eq!(known_chains.chain_from(dog), Some(reversed));
+    }
+
+    #[test]
+    fn a_chain_through_a_word_does_not_stop_a_longer_search_from_it() {
+        let graph = graph();
+        let csr = graph.to_csr();
+        let dp = DpSolver::new(&graph);
+        let known_chains = KnownChains::new(graph.size());
+
+        // From "cot" this chain only gives "cot cog dog", but going the other
+        // way round the triangle gives "cot cat cut hut hit".
+        known_chains.offer(&path(&graph, &["cat", "cot", "cog", "dog"]));
+
+        let cot = graph.get_index_for_word("cot");
+        let incumbent = known_chains.incumbent(cot);
+        assert_eq!(incumbent.get(), 3);
+
+        let found = search::longest_path_from(&csr, cot, graph.size(), Some(incumbent), None);
+        assert_eq!(found.len(), dp.longest_path_from(cot, None).len());
+        assert_eq!(found.len(), 5);
+        known_chains.offer(&found);
+        assert_eq!(known_chains.chain_from(cot), Some(found));
+
+        // A shorter chain offered later does not replace it.
+        known_chains.offer(&path(&graph, &["dog", "cog", "cot", "cut"]));
+        assert_eq!(known_chains.chain_from(cot).map(|chain| chain.len()), Some(5));
+    }
+}
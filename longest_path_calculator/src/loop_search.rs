@@ -0,0 +1,119 @@
+use graph::{CsrGraph, Graph};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::search::{self, Incumbent};
+
+/// Calculates the longest loop in a graph: a chain of at least 3 words whose
+/// last word is also one letter away from its first, so that it closes up.
+///
+/// A loop can never pass through a cut vertex twice, so it lies entirely inside
+/// one block of the block-cut tree, and only blocks of 3 or more vertices can
+/// hold one. Within a block with vertices `v_0, v_1, ..., v_k`, every loop has a
+/// first vertex in that order, say `v_i`, and is then a path from `v_i` back to
+/// a neighbour of `v_i` which only uses `v_i, ..., v_k`. So one search from each
+/// vertex, with the vertices before it removed, finds every loop. The searches
+/// are independent, and share an incumbent, so they are run in parallel.
+pub struct LoopSolver<'a> {
+    graph: &'a Graph,
+    /// The blocks which are large enough to hold a loop.
+    blocks: Vec<Vec<usize>>,
+}
+
+impl<'a> LoopSolver<'a> {
+    pub fn new(graph: &'a Graph) -> Self {
+        let blocks = graph
+            .block_cut_tree()
+            .blocks
+            .into_iter()
+            .filter(|block| block.len() >= 3)
+            .collect();
+
+        Self { graph, blocks }
+    }
+
+    /// Returns a cap on the length of any loop, which is the size of the
+    /// largest block.
+    pub fn upper_bound(&self) -> usize {
+        self.blocks.iter().map(|block| block.len()).max().unwrap_or(0)
+    }
+
+    /// Returns the longest loop in the graph, as a list of vertex indices
+    /// which does not repeat the first vertex at the end. Only loops longer
+    /// than `incumbent` are searched for, so if there are none the result may
+    /// be shorter than it, or empty.
+    pub fn longest_loop(&self, incumbent: &Incumbent) -> Vec<usize> {
+        let block_graphs: Vec<Graph> = self.blocks.iter().map(|block| self.graph.subgraph(block)).collect();
+
+        // Start with the searches of the largest graphs, which are the most
+        // likely to find long loops and so to prune the others.
+        let mut searches: Vec<(usize, usize)> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(b, block)| (0..block.len() - 2).map(move |i| (b, i)))
+            .collect();
+        searches.sort_by_key(|&(b, i)| std::cmp::Reverse(self.blocks[b].len() - i));
+
+        searches
+            .into_par_iter()
+            .map(|(b, i)| {
+                let block = &self.blocks[b];
+                if block.len() - i <= incumbent.get() {
+                    return Vec::new();
+                }
+
+                let csr = without_earlier_vertices(&block_graphs[b], i);
+                let path = search::longest_loop_through(&csr, i, block.len() - i, Some(incumbent));
+                path.iter().map(|&v| block[v]).collect()
+            })
+            .max_by_key(|path: &Vec<usize>| path.len())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns a copy of `graph` in which the vertices before `first` have no edges.
+fn without_earlier_vertices(graph: &Graph, first: usize) -> CsrGraph {
+    let adjacency_lists: Vec<Vec<usize>> = (0..graph.size())
+        .map(|v| {
+            if v < first {
+                Vec::new()
+            } else {
+                graph.neighbours(v).filter(|&a| a >= first).collect()
+            }
+        })
+        .collect();
+
+    CsrGraph::from_adjacency_lists(adjacency_lists.iter().map(Vec::as_slice))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_graphs::{all_paths_from, assert_is_path_from, random_graph};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn longest_loop_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(13);
+
+        for _ in 0..200 {
+            let graph = random_graph(&mut rng);
+            let solver = LoopSolver::new(&graph);
+
+            let longest = (0..graph.size())
+                .flat_map(|start_idx| all_paths_from(&graph, start_idx))
+                .filter(|path| path.len() >= 3 && graph.is_adjacent(path[0], *path.last().unwrap()))
+                .map(|path| path.len())
+                .max()
+                .unwrap_or(0);
+
+            let path = solver.longest_loop(&Incumbent::new(0));
+            assert_eq!(path.len(), longest);
+            assert!(path.len() <= solver.upper_bound());
+            if let (Some(&first), Some(&last)) = (path.first(), path.last()) {
+                assert_is_path_from(&graph, &path, first);
+                assert!(graph.is_adjacent(first, last), "The loop does not close up");
+            }
+        }
+    }
+}
@@ -1,15 +1,55 @@
-use std::{collections::{HashMap, HashSet}, fs::File, hash::Hash, io, num, path::PathBuf};
-use std::io::Write;
-
-use completed_words::{CompletedWords, create_chain_directories, get_completed_words};
-use graph::{Graph, RelativeDirectories};
-use rayon::{
-    iter::{IntoParallelIterator, ParallelIterator},
-    vec,
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::Duration,
 };
+
+use block_search::BlockSolver;
+use chain_file::{read_chain_file, write_chain_file, write_chain_list_file, ChainFile, Proof, Provenance};
+use completed_words::{
+    create_chain_directories, get_checkpointed_words, get_completed_words, CompletedWords,
+};
+use dp_search::{DpSolver, MAX_DP_SIZE};
+use heuristic::HeuristicOptions;
+use known_chains::KnownChains;
+use loop_search::LoopSolver;
+use progress::Progress;
+use reduced_search::ReducedSolver;
+use sat_search::SatSolver;
+use search::Incumbent;
+use summary::{
+    summarise_chains, summarise_loops, write_longest_chains_file, write_longest_loops_file,
+    write_longest_path_file,
+};
+use graph::{calculate_graph_stats, CsrGraph, Graph, GraphError, RelativeDirectories};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use structopt::StructOpt;
 
+mod between_search;
+mod bitset;
+mod block_search;
+mod chain_file;
+mod checkpoint;
 mod completed_words;
+mod dp_search;
+mod heuristic;
+mod known_chains;
+mod loop_search;
+mod progress;
+mod reduced_search;
+mod sat_search;
+mod search;
+mod summary;
+#[cfg(test)]
+mod test_graphs;
+mod validate;
+
+/// The word lengths to consider when none are specified.
+const ALL_WORD_LENGTHS: Range<usize> = 1..30;
 
 #[derive(Debug, StructOpt)]
 struct CommandLineOptions {
@@ -17,6 +57,155 @@ struct CommandLineOptions {
     dictionary_directory: PathBuf,
     #[structopt(short = "n", long, help = "Comma-separated list of word length to calculate for")]
     word_lengths: Option<String>,
+    #[structopt(
+        long,
+        default_value = "blocks",
+        help = "Algorithm to use: 'blocks' (search each block of the block-cut tree), \
+                'reduced' (search the graph with leaves trimmed and degree-2 runs contracted), \
+                'backtrack' (search the whole graph), 'sat' (prove the longest chain with a SAT solver), \
+                'dp' (dynamic programming over subsets of the words, for graphs of at most 30 words) \
+                or 'heuristic' (search for a good chain within a time limit)"
+    )]
+    solver: Solver,
+    #[structopt(
+        long,
+        default_value = "per-word",
+        help = "What to calculate: 'per-word' (the longest chain from every word), \
+                'global' (only the longest chain in the graph), \
+                'loop' (the longest loop in the graph, always found by exhaustive search) \
+                or 'count' (count the chains as long as the longest chain already found, by exhaustive search)"
+    )]
+    mode: Mode,
+    #[structopt(
+        long,
+        default_value = "1000",
+        help = "Maximum number of chains that 'count' mode lists in 'all_longest_chains_NN.txt'"
+    )]
+    max_chains: usize,
+    #[structopt(
+        long,
+        default_value = "60",
+        help = "Number of seconds the heuristic solver spends on each word"
+    )]
+    time_limit: u64,
+    #[structopt(long, default_value = "0", help = "Random number seed for the heuristic solver")]
+    seed: u64,
+    #[structopt(
+        long,
+        help = "Write the SAT solver's proofs that chains are the longest to 'proofs_NN', for checking with drat-trim"
+    )]
+    write_proofs: bool,
+    #[structopt(
+        long,
+        default_value = "60",
+        help = "Number of seconds between progress reports, which are also appended to 'progress_NN.csv', or 0 for none"
+    )]
+    progress_interval: u64,
+    #[structopt(
+        long,
+        default_value = "20",
+        help = "Graphs of at most this many words (up to 30) are solved by dynamic programming \
+                instead of by the 'blocks', 'reduced' or 'backtrack' solver"
+    )]
+    dp_max_size: usize,
+    #[structopt(
+        long,
+        help = "Search every component of each graph, from 'all_adjacency_lists_NN.txt', \
+                rather than only the largest, \
+                skipping those too small to hold a longer chain than one already found"
+    )]
+    all_components: bool,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+/// Things to do instead of calculating chains for every word length.
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Finds the longest chain from one word to another, such as for a word
+    /// ladder puzzle. Uses the heuristic if `--solver heuristic` is given, and
+    /// an exhaustive search otherwise.
+    Between {
+        #[structopt(name = "FROM")]
+        from: String,
+        #[structopt(name = "TO")]
+        to: String,
+    },
+    /// Checks that chain files are valid: every word is in 'corpus.txt',
+    /// all the words are the same length, none is repeated, and each is one
+    /// letter different from the next. Directories stand for every chain
+    /// file in them, and with no files every 'chains_NN' directory is
+    /// checked. Exits with an error if any file is invalid.
+    Validate {
+        #[structopt(name = "FILE", parse(from_os_str))]
+        files: Vec<PathBuf>,
+    },
+}
+
+/// The algorithm used to calculate the longest path from each start word.
+#[derive(Debug, Clone, Copy)]
+enum Solver {
+    /// Exhaustive backtracking over the entire graph.
+    Backtrack,
+    /// Exhaustive backtracking within each block of the block-cut tree,
+    /// with the per-block results combined along the tree.
+    Blocks,
+    /// Exhaustive backtracking over the reduced graph, with the result
+    /// expanded back into words.
+    Reduced,
+    /// A SAT solver, which proves that the chains it finds are the longest.
+    Sat,
+    /// Dynamic programming over the subsets of the vertices, which is exact
+    /// and fast for small graphs.
+    Dp,
+    /// A randomized search which finds good, but not necessarily the
+    /// longest, chains within a time limit.
+    Heuristic,
+}
+
+impl FromStr for Solver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "backtrack" => Ok(Solver::Backtrack),
+            "blocks" => Ok(Solver::Blocks),
+            "reduced" => Ok(Solver::Reduced),
+            "sat" => Ok(Solver::Sat),
+            "dp" => Ok(Solver::Dp),
+            "heuristic" => Ok(Solver::Heuristic),
+            _ => Err(format!("Unknown solver '{}'", s)),
+        }
+    }
+}
+
+/// What is calculated for each graph.
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// The longest chain starting at each word.
+    PerWord,
+    /// The longest chain in the graph, from whichever word it starts at.
+    Global,
+    /// The longest loop in the graph, i.e. a chain whose last word is also
+    /// one letter away from its first.
+    Loop,
+    /// The number of chains as long as the longest one found, a chain and its
+    /// reverse counting as one.
+    Count,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "per-word" => Ok(Mode::PerWord),
+            "global" => Ok(Mode::Global),
+            "loop" => Ok(Mode::Loop),
+            "count" => Ok(Mode::Count),
+            _ => Err(format!("Unknown mode '{}'", s)),
+        }
+    }
 }
 
 fn main() {
@@ -31,19 +220,48 @@ fn main() {
         std::process::exit(1);
     }
 
-    let mut word_lengths: Vec<usize> = match options.word_lengths {
+    if options.dp_max_size > MAX_DP_SIZE {
+        eprintln!("--dp-max-size cannot be more than {}", MAX_DP_SIZE);
+        std::process::exit(1);
+    }
+
+    if let Some(Command::Between { from, to }) = &options.command {
+        find_chain_between(&dirs, from, to, &options);
+        return;
+    }
+
+    if let Some(Command::Validate { files }) = &options.command {
+        validate_chains(&dirs, files);
+        return;
+    }
+
+    let mut word_lengths: Vec<usize> = match &options.word_lengths {
         Some(lengths) => lengths.split(',').map(|w| w.parse().unwrap()).collect(),
-        None => (1..30).collect()
+        None => ALL_WORD_LENGTHS.collect()
     };
     
     word_lengths.sort_unstable();
     
-    let mut graphs = load_graphs(&dirs, &word_lengths);
+    let mut graphs = load_graphs(&dirs, &word_lengths, options.all_components);
     if graphs.is_empty() {
         eprintln!("No files found in output directory {:?}, run pre-calc first", dirs.output_directory());
         std::process::exit(1);
     }
 
+    // Sort graphs by increasing size so that we handle the smallest ones first.
+    // Then it looks like we are making progress...
+    graphs.sort_unstable_by_key(|a| a.size());
+
+    if let Mode::Loop = options.mode {
+        calculate_loops(&dirs, &graphs);
+        return;
+    }
+
+    if let Mode::Count = options.mode {
+        count_longest_chains(&dirs, &graphs, options.max_chains);
+        return;
+    }
+
     // Create a set of output directories based on what graphs we actually loaded.
     let word_lengths: Vec<_> = graphs.iter().map(|g| g.word_length()).collect();
     create_chain_directories(&dirs, &word_lengths);
@@ -52,35 +270,76 @@ fn main() {
     let completed_words = get_completed_words(&dirs, &word_lengths);
     print_completion_status(&graphs, &completed_words);
 
-    // Sort graphs by increasing size so that we handle the smallest ones first.
-    // Then it looks like we are making progress...
-    graphs.sort_unstable_by(|a, b| a.size().cmp(&b.size()));
-
     // Calculate remaining words in the above order.
     for graph in &graphs {
         let completed_already = completed_words.completed_words_of_length(graph.word_length());
-        calculate_longest_path(&dirs, graph, completed_already);
+        if options.all_components {
+            calculate_longest_path_per_component(&dirs, graph, completed_already, &options);
+        } else {
+            calculate_longest_path(&dirs, graph, completed_already, None, &options);
+        }
     }
+
+    // Summarise every word length, not just the ones calculated by this
+    // run, so that the overall summary is always complete.
+    let summaries: Vec<_> = ALL_WORD_LENGTHS
+        .filter_map(|word_length| summarise_chains(&dirs, word_length))
+        .collect();
+
+    for summary in &summaries {
+        write_longest_path_file(&dirs, summary);
+    }
+
+    write_longest_chains_file(&dirs, &summaries);
 }
 
-fn load_graphs(dirs: &RelativeDirectories, word_lengths: &[usize]) -> Vec<Graph> {
+/// Loads the graph of each word length, which is just its largest component
+/// unless `all_components` is set.
+fn load_graphs(dirs: &RelativeDirectories, word_lengths: &[usize], all_components: bool) -> Vec<Graph> {
     word_lengths
         .into_par_iter()
         .filter_map(|word_length| {
-            let filename = dirs.largest_component_adjacency_file(*word_length);
-            let g_result = Graph::load_from_adjacency_file(&filename);
-            if g_result.is_ok() {
-                println!(
-                    "Loaded graph of size {} from {:?}",
-                    g_result.as_ref().unwrap().size(),
-                    filename
-                );
+            let (graph_file, filename) = if all_components {
+                (dirs.all_graph_file(*word_length), dirs.all_adjacency_file(*word_length))
+            } else {
+                (
+                    dirs.largest_component_graph_file(*word_length),
+                    dirs.largest_component_adjacency_file(*word_length),
+                )
+            };
+            match load_graph(&graph_file, &filename) {
+                Ok((g, filename)) => {
+                    println!(
+                        "Loaded graph of size {} from {:?}",
+                        g.size(),
+                        filename
+                    );
+                    Some(g)
+                }
+                // Not every word length has words.
+                Err(GraphError::Io { error, .. }) if error.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    eprintln!("Unable to load the graph of word length {}: {}", word_length, e);
+                    None
+                }
             }
-            g_result.ok()
         })
         .collect()
 }
 
+/// Loads a graph from its binary file, or from its adjacency file if there is
+/// no binary file, as with older versions of pre-calc, or it cannot be read.
+/// Also returns the name of the file the graph was loaded from.
+fn load_graph<'a>(graph_file: &'a Path, adjacency_file: &'a Path) -> Result<(Graph, &'a Path), GraphError> {
+    match Graph::load_from_binary_file(graph_file) {
+        Ok(graph) => return Ok((graph, graph_file)),
+        Err(GraphError::Io { error, .. }) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("Unable to load the graph, reading {:?} instead: {}", adjacency_file, e),
+    }
+
+    Graph::load_from_adjacency_file(adjacency_file).map(|graph| (graph, adjacency_file))
+}
+
 fn print_completion_status(graphs: &[Graph], completed_words: &CompletedWords) {
     for graph in graphs {
         let word_length = graph.word_length();
@@ -95,74 +354,767 @@ fn print_completion_status(graphs: &[Graph], completed_words: &CompletedWords) {
     }
 }
 
-fn calculate_longest_path(dirs: &RelativeDirectories, graph: &Graph, completed_already: &[String]) {
-    let all_words: HashSet<String> = graph.vertices.iter().map(|v| v.word.clone()).collect();
-    let completed_already: HashSet<String> = completed_already.iter().cloned().collect();
-    let words_still_to_do: Vec<_> = all_words.difference(&completed_already).collect();
-    if words_still_to_do.len() == 0 {
-        return;
+/// Calculates the chains of a graph, as chosen by the options, skipping the
+/// words whose chains are in `completed_already`. Returns the longest chain
+/// known in the graph afterwards, and whether it is proven to be the longest,
+/// or None if the graph could not be solved.
+///
+/// If the graph is one component of a larger graph, `whole_graph_upper_bound`
+/// is the upper bound on the longest chain in the larger graph. That is the
+/// bound recorded in the chain files, and as the longest chain in the
+/// component need not be the longest in the larger graph, it is not recorded
+/// as such.
+fn calculate_longest_path(
+    dirs: &RelativeDirectories,
+    graph: &Graph,
+    completed_already: &[String],
+    whole_graph_upper_bound: Option<usize>,
+    options: &CommandLineOptions,
+) -> Option<(Vec<usize>, bool)> {
+    let all_words: HashSet<String> = (0..graph.size()).map(|idx| graph.word(idx).to_string()).collect();
+    let completed: HashSet<String> = completed_already.iter().cloned().collect();
+    let mut words_still_to_do: Vec<_> = all_words.difference(&completed).collect();
+    if words_still_to_do.is_empty() {
+        let (known_chains, completed_proven) = read_known_chains(dirs, graph, completed_already);
+        return Some((known_chains.longest().unwrap_or_default(), completed_proven));
     }
 
+    // Resume any interrupted searches first, then do the rest in order.
+    let checkpointed_words = get_checkpointed_words(dirs, graph.word_length());
+    words_still_to_do.sort_unstable_by_key(|&word| (!checkpointed_words.contains(word), word));
+
+    // No chain can be longer than this, so there is no need to search
+    // any further once one has been found.
+    let upper_bound = whole_graph_upper_bound
+        .unwrap_or_else(|| calculate_graph_stats(graph).largest_component_upper_bound());
+
     println!(
-        "There are {} words still to compute for the graph of word length {}",
+        "There are {} words still to compute for the graph of word length {} (upper bound on chain length is {})",
         words_still_to_do.len(),
-        graph.word_length()
+        graph.word_length(),
+        upper_bound
     );
 
-    words_still_to_do.into_par_iter().for_each(|word| {
-        let path = calculate_longest_path_for_word(dirs, graph, word);
-        let path_in_words = path.iter().map(|idx| graph.vertices[*idx].word.clone()).collect();
-        write_path_output_file(dirs, &path_in_words);
+    let solver = match options.solver {
+        Solver::Backtrack | Solver::Blocks | Solver::Reduced if graph.size() <= options.dp_max_size => {
+            println!(
+                "Using dynamic programming for the graph of word length {}, as it has only {} words",
+                graph.word_length(),
+                graph.size()
+            );
+            PreparedSolver::Dp(DpSolver::new(graph))
+        }
+        Solver::Dp if graph.size() > MAX_DP_SIZE => {
+            println!(
+                "The graph of word length {} has {} words, which is too many to solve by dynamic programming",
+                graph.word_length(),
+                graph.size()
+            );
+            return None;
+        }
+        Solver::Dp => PreparedSolver::Dp(DpSolver::new(graph)),
+        Solver::Backtrack => PreparedSolver::Backtrack(graph.to_csr()),
+        Solver::Blocks => PreparedSolver::Blocks(BlockSolver::new(
+            graph,
+            Some(dirs.chains_directory(graph.word_length())),
+        )),
+        Solver::Reduced => {
+            let reduced_solver = ReducedSolver::new(graph, upper_bound);
+            println!(
+                "Reduced the graph of word length {} from {} to {} vertices",
+                graph.word_length(),
+                graph.size(),
+                reduced_solver.reduced_size()
+            );
+            PreparedSolver::Reduced(reduced_solver)
+        }
+        Solver::Sat => PreparedSolver::Sat(SatSolver::new(
+            graph,
+            upper_bound,
+            if options.write_proofs {
+                Some(dirs.proofs_directory(graph.word_length()))
+            } else {
+                None
+            },
+        )),
+        Solver::Heuristic => PreparedSolver::Heuristic(HeuristicOptions {
+            time_limit: Duration::from_secs(options.time_limit),
+            seed: options.seed,
+            upper_bound,
+        }),
+    };
+
+    let (known_chains, completed_proven) = read_known_chains(dirs, graph, completed_already);
+
+    let progress = Progress::new(
+        graph.word_length(),
+        words_still_to_do.len(),
+        known_chains.longest().map_or(0, |chain| chain.len()),
+        dirs.progress_file(graph.word_length()),
+    );
+
+    // Only the caller knows whether the longest chain in a component is the
+    // longest in the whole graph.
+    let can_claim_longest = completed_proven && whole_graph_upper_bound.is_none();
+
+    let mut longest = Vec::new();
+    progress.report_while(Duration::from_secs(options.progress_interval), || {
+        longest = match options.mode {
+            Mode::Loop => unreachable!("Loops are calculated by calculate_loops"),
+            Mode::Count => unreachable!("Chains are counted by count_longest_chains"),
+            Mode::PerWord => calculate_chain_per_word(
+                dirs,
+                graph,
+                &words_still_to_do,
+                &solver,
+                &known_chains,
+                upper_bound,
+                can_claim_longest,
+                &progress,
+            ),
+            Mode::Global => calculate_global_chain(
+                dirs,
+                graph,
+                &words_still_to_do,
+                &solver,
+                &known_chains,
+                upper_bound,
+                can_claim_longest,
+                &progress,
+            ),
+        }
     });
 
-    // ALL DONE BY HERE!
-    // scan files in directory chainsNN
-    // find the file with the longest number of words (split by space)
-    // write file chainsNN\00_longest_path.txt
+    Some((longest, solver.is_exact() && completed_proven))
 }
 
-fn calculate_longest_path_for_word(
+/// Calculates the chains of each component of a graph which could hold the
+/// longest chain, largest first, so that the longest chain is known to be in
+/// the component it was found in rather than assumed to be in the largest. A
+/// component with no more words than the longest chain found so far cannot
+/// hold a longer one, so it and the smaller components after it are skipped.
+/// Once every other component has been solved or skipped, the longest chain
+/// is recorded as the longest in the graph if all the solutions were exact.
+fn calculate_longest_path_per_component(
+    dirs: &RelativeDirectories,
+    graph: &Graph,
+    completed_already: &[String],
+    options: &CommandLineOptions,
+) {
+    let mut component_vertices: HashMap<usize, Vec<usize>> = HashMap::new();
+    for idx in 0..graph.size() {
+        component_vertices.entry(graph.component(idx)).or_default().push(idx);
+    }
+
+    // A single word is not a chain, so those components are never searched.
+    let components: Vec<Graph> = graph
+        .components()
+        .iter()
+        .filter(|component| component.num_vertices > 1)
+        .map(|component| graph.subgraph(&component_vertices[&component.number]))
+        .collect();
+
+    let upper_bound = components
+        .iter()
+        .map(|component| calculate_graph_stats(component).largest_component_upper_bound())
+        .max()
+        .unwrap_or(0);
+
+    let mut longest: Vec<String> = Vec::new();
+    let mut all_proven = true;
+
+    for (i, component) in components.iter().enumerate() {
+        if component.size() <= longest.len() {
+            println!(
+                "Skipping the {} remaining components of word length {}, as none has more than {} words so none can hold a chain longer than {}",
+                components.len() - i,
+                graph.word_length(),
+                component.size(),
+                longest.len()
+            );
+            break;
+        }
+
+        let words: HashSet<&str> = (0..component.size()).map(|idx| component.word(idx)).collect();
+        let completed: Vec<String> = completed_already
+            .iter()
+            .filter(|word| words.contains(word.as_str()))
+            .cloned()
+            .collect();
+
+        match calculate_longest_path(dirs, component, &completed, Some(upper_bound), options) {
+            Some((path, proven)) => {
+                all_proven &= proven;
+                if path.len() > longest.len() {
+                    longest = path.iter().map(|&idx| component.word(idx).to_string()).collect();
+                }
+            }
+            None => all_proven = false,
+        }
+    }
+
+    if all_proven && longest.len() > 1 {
+        println!(
+            "The longest chain of word length {} has been proven to be the longest in any component",
+            graph.word_length()
+        );
+
+        let chain_file = ChainFile {
+            provenance: Some(Provenance::new(true, longest.len(), upper_bound).proven_by(Proof::EveryComponent)),
+            chain: longest,
+        };
+        write_path_output_file(dirs, &chain_file);
+    }
+}
+
+/// Calculates the longest chain from each word in `words`. Every chain found is
+/// added to `known_chains`, and the search from each word only looks for chains
+/// longer than the one already known from it. `completed_proven` says whether
+/// the chains already written for the other words are all proven longest.
+/// Returns the longest chain known from any word.
+#[allow(clippy::too_many_arguments)]
+fn calculate_chain_per_word(
     dirs: &RelativeDirectories,
     graph: &Graph,
-    word: &String,
+    words: &[&String],
+    solver: &PreparedSolver,
+    known_chains: &KnownChains,
+    upper_bound: usize,
+    completed_proven: bool,
+    progress: &Progress,
 ) -> Vec<usize> {
-    // Initialise tracking structures. They are based on vertex indices.
-    // This capacity is the longest possible path, so we will never need
-    // to grow this vector.
-    let mut longest_path = Vec::with_capacity(graph.size());
-    let mut visited_vertices = vec![false; graph.size()];
+    if let PreparedSolver::Blocks(block_solver) = solver {
+        block_solver.prepare(&start_indices(graph, words));
+    }
 
-    let start_idx = graph.get_index_for_word(word);
-    calc_lp(graph, start_idx, &mut longest_path, &mut visited_vertices);
+    words.into_par_iter().for_each(|word| {
+        let start_idx = graph.get_index_for_word(word);
+        let incumbent = known_chains.incumbent(start_idx);
+
+        // A chain as long as the upper bound cannot be beaten, so there is
+        // nothing to search for.
+        if incumbent.get() < upper_bound {
+            let path = match solver {
+                PreparedSolver::Backtrack(csr) => calculate_longest_path_for_word(
+                    dirs,
+                    graph,
+                    csr,
+                    word,
+                    upper_bound,
+                    Some(incumbent),
+                ),
+                PreparedSolver::Blocks(block_solver) => block_solver.longest_path_from(start_idx, Some(incumbent)),
+                PreparedSolver::Reduced(reduced_solver) => {
+                    reduced_solver.longest_path_from(start_idx, Some(incumbent))
+                }
+                PreparedSolver::Sat(sat_solver) => {
+                    sat_solver.longest_path_from(start_idx, Some(incumbent))
+                }
+                PreparedSolver::Dp(dp_solver) => dp_solver.longest_path_from(start_idx, Some(incumbent)),
+                PreparedSolver::Heuristic(heuristic_options) => {
+                    // The heuristic writes its best chain as it goes along.
+                    heuristic::longest_path_from(graph, start_idx, heuristic_options, |path| {
+                        known_chains.offer(path);
+                        write_known_chain(dirs, graph, known_chains, start_idx, false, upper_bound);
+                    });
+                    progress.word_done(known_chains.incumbent(start_idx).get());
+                    return;
+                }
+            };
+
+            known_chains.offer(&path);
+        }
+
+        write_known_chain(dirs, graph, known_chains, start_idx, solver.is_exact(), upper_bound);
+        progress.word_done(known_chains.incumbent(start_idx).get());
+    });
+
+    // Every word now has a chain, so if they are all the longest from their
+    // start words then the longest of them is the longest in the graph.
+    let longest = known_chains.longest().unwrap_or_default();
+    if solver.is_exact() && completed_proven {
+        write_longest_chain(dirs, graph, &longest, Some(Proof::EveryStartWord), true, upper_bound);
+    }
+
+    longest
+}
+
+/// Calculates the single longest chain in the graph, by searching from each of
+/// `words` for a chain longer than any found so far. The searches share one
+/// incumbent, so most of them are cut short, and only the start words whose
+/// chain could be the longest get a chain file. The longest chain is also the
+/// longest from its last word, so that gets a chain file too. `completed_proven`
+/// says whether the chains already written for the other words are all proven
+/// longest. Returns the longest chain found.
+#[allow(clippy::too_many_arguments)]
+fn calculate_global_chain(
+    dirs: &RelativeDirectories,
+    graph: &Graph,
+    words: &[&String],
+    solver: &PreparedSolver,
+    known_chains: &KnownChains,
+    upper_bound: usize,
+    completed_proven: bool,
+    progress: &Progress,
+) -> Vec<usize> {
+    let longest = Mutex::new(known_chains.longest().unwrap_or_default());
+    let incumbent = Incumbent::new(longest.lock().unwrap().len());
+
+    // The SAT and dynamic programming solvers can leave the start word open,
+    // so a single solve finds the longest chain, and proves it, without
+    // trying each word in turn.
+    let single_solve = match solver {
+        PreparedSolver::Sat(sat_solver) => Some(sat_solver.longest_path(Some(&incumbent))),
+        PreparedSolver::Dp(dp_solver) => Some(dp_solver.longest_path()),
+        _ => None,
+    };
+
+    let searched_whole_graph = single_solve.is_some();
+    if let Some(path) = single_solve {
+        let mut longest = longest.lock().unwrap();
+        if path.len() > longest.len() {
+            write_chain(dirs, graph, &path, Provenance::new(true, path.len(), upper_bound));
+            *longest = path;
+        }
+    } else {
+        if let PreparedSolver::Blocks(block_solver) = solver {
+            block_solver.prepare(&start_indices(graph, words));
+        }
+
+        words.into_par_iter().for_each(|word| {
+            if incumbent.get() >= upper_bound {
+                progress.word_done(0);
+                return;
+            }
+
+            let start_idx = graph.get_index_for_word(word);
+            let path = match solver {
+                PreparedSolver::Backtrack(csr) => calculate_longest_path_for_word(
+                    dirs,
+                    graph,
+                    csr,
+                    word,
+                    upper_bound,
+                    Some(&incumbent),
+                ),
+                PreparedSolver::Blocks(block_solver) => block_solver.longest_path_from(start_idx, Some(&incumbent)),
+                PreparedSolver::Reduced(reduced_solver) => {
+                    reduced_solver.longest_path_from(start_idx, Some(&incumbent))
+                }
+                PreparedSolver::Sat(_) | PreparedSolver::Dp(_) => {
+                    unreachable!("The solver searches from every word at once")
+                }
+                PreparedSolver::Heuristic(heuristic_options) => {
+                    heuristic::longest_path_from(graph, start_idx, heuristic_options, |path| {
+                        incumbent.offer(path.len())
+                    })
+                }
+            };
+
+            incumbent.offer(path.len());
+            progress.word_done(path.len());
+
+            // If nothing longer has been found by any search then this search was
+            // never cut short below the length of its own result, so that is the
+            // longest chain from this word.
+            let mut longest = longest.lock().unwrap();
+            if path.len() >= incumbent.get() {
+                let provenance = Provenance::new(solver.is_exact(), path.len(), upper_bound);
+                write_chain(dirs, graph, &path, provenance);
+                if path.len() > longest.len() {
+                    *longest = path;
+                }
+            }
+        });
+    }
+
+    let mut longest = longest.into_inner().unwrap();
+    longest.reverse();
+    let proof = if !solver.is_exact() || !completed_proven {
+        None
+    } else if searched_whole_graph {
+        Some(Proof::WholeGraph)
+    } else {
+        Some(Proof::EveryStartWord)
+    };
+    write_longest_chain(dirs, graph, &longest, proof, solver.is_exact(), upper_bound);
+
+    longest
+}
 
-    longest_path
+/// Returns the vertex indices of `words`.
+fn start_indices(graph: &Graph, words: &[&String]) -> Vec<usize> {
+    words.iter().map(|word| graph.get_index_for_word(word)).collect()
 }
 
-fn calc_lp(graph: &Graph, vertex_index: usize, longest_path: &mut Vec<usize>, visited_vertices: &mut Vec<bool>) {
-    longest_path.push(vertex_index);
-    visited_vertices[vertex_index] = true;
+/// A solver together with anything it has precalculated for the graph.
+enum PreparedSolver<'a> {
+    Backtrack(CsrGraph),
+    Blocks(BlockSolver<'a>),
+    Reduced(ReducedSolver),
+    Sat(SatSolver<'a>),
+    Dp(DpSolver),
+    Heuristic(HeuristicOptions),
+}
 
-    // Now search down.
-    let vertex = &graph.vertices[vertex_index];
-    for adjacency_index in &vertex.adjacency_list {
-        if !visited_vertices[*adjacency_index] {
-            calc_lp(graph, *adjacency_index, longest_path, visited_vertices);
+impl PreparedSolver<'_> {
+    /// Returns true if the solver always finds the longest chain.
+    fn is_exact(&self) -> bool {
+        !matches!(self, PreparedSolver::Heuristic(_))
+    }
+}
+
+/// Validates the chain files `files`, or every chain file if there are none,
+/// and exits with an error if any are invalid.
+fn validate_chains(dirs: &RelativeDirectories, files: &[PathBuf]) {
+    let corpus = match validate::read_corpus(&dirs.corpus_file()) {
+        Ok(corpus) => corpus,
+        Err(e) => {
+            eprintln!("Unable to read the corpus {:?}, run pre-calc first: {}", dirs.corpus_file(), e);
+            std::process::exit(1);
         }
+    };
+
+    let files = if files.is_empty() {
+        ALL_WORD_LENGTHS
+            .map(|word_length| dirs.chains_directory(word_length))
+            .filter(|dir| dir.is_dir())
+            .collect()
+    } else {
+        files.to_vec()
+    };
+
+    if !validate::validate_chain_files(&corpus, &files) {
+        std::process::exit(1);
     }
 }
 
-/// Writes the output file 'output\chainsNN\{word}.txt'.
-fn write_path_output_file(dirs: &RelativeDirectories, path: &Vec<String>) {
-    assert!(path.len() > 1);
-    let anchor_word = &path[0];
-    let mut filename = dirs.chains_directory(anchor_word.len());
-    std::fs::create_dir_all(&filename).unwrap();
-    filename.push(format!("{}.txt", anchor_word));
+/// Finds the longest chain from `from` to `to` and prints it. This loads the
+/// graph of all the words of their length, not just the largest component, so
+/// that any two words can be given.
+fn find_chain_between(dirs: &RelativeDirectories, from: &str, to: &str, options: &CommandLineOptions) {
+    let from = from.to_lowercase();
+    let to = to.to_lowercase();
+    if from.len() != to.len() {
+        eprintln!("'{}' and '{}' are not the same length", from, to);
+        std::process::exit(1);
+    }
+
+    let graph = match load_graph(&dirs.all_graph_file(from.len()), &dirs.all_adjacency_file(from.len())) {
+        Ok((graph, _)) => graph,
+        Err(e) => {
+            eprintln!("Unable to load the graph, run pre-calc first: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let index_of = |word: &str| match graph.find(word) {
+        Some(idx) => idx,
+        None => {
+            eprintln!("'{}' is not in the dictionary", word);
+            std::process::exit(1);
+        }
+    };
+    let (start_idx, end_idx) = (index_of(&from), index_of(&to));
 
-    let out_file = File::create(&filename).expect("Unable to create chain file");
-    let mut writer = io::BufWriter::new(out_file);
-    let words = path.join(" ");
-    writeln!(writer, "{}", words).unwrap();
+    let path = match options.solver {
+        Solver::Heuristic => {
+            // No chain can have more words than the component they are in.
+            let component = graph.component(start_idx);
+            let upper_bound = graph.component_vertices(component).count();
+            let heuristic_options = HeuristicOptions {
+                time_limit: Duration::from_secs(options.time_limit),
+                seed: options.seed,
+                upper_bound,
+            };
+
+            heuristic::longest_path_between(&graph, start_idx, end_idx, &heuristic_options, |path| {
+                println!("Found a chain of length {}", path.len())
+            })
+        }
+        _ => between_search::longest_path_between(&graph, start_idx, end_idx),
+    };
+
+    if path.is_empty() {
+        println!("There is no chain from {} to {}", from, to);
+        return;
+    }
+
+    let words: Vec<_> = path.iter().map(|idx| graph.word(*idx)).collect();
+    match options.solver {
+        Solver::Heuristic => println!(
+            "The longest chain found from {} to {} has {} words:",
+            from,
+            to,
+            path.len()
+        ),
+        _ => println!("The longest chain from {} to {} has {} words:", from, to, path.len()),
+    }
+    println!("{}", words.join(" "));
+}
+
+/// Counts the chains in each graph which are as long as the longest chain
+/// already found for it, and lists up to `max_chains` of them in
+/// 'output\all_longest_chains_NN.txt'. A chain and its reverse count as one.
+fn count_longest_chains(dirs: &RelativeDirectories, graphs: &[Graph], max_chains: usize) {
+    for graph in graphs {
+        let summary = match summarise_chains(dirs, graph.word_length()) {
+            Some(summary) => summary,
+            None => {
+                println!(
+                    "No chains have been calculated for word length {}, so there are none to count",
+                    graph.word_length()
+                );
+                continue;
+            }
+        };
+
+        let length = summary.longest_chain.len();
+        println!(
+            "Counting the chains of {} words for the graph of word length {}",
+            length,
+            graph.word_length()
+        );
+
+        // Each search only finds the chains ending at a later word than it
+        // starts from, so every chain is found from just one of its ends.
+        let csr = graph.to_csr();
+        let found: Vec<_> = (0..graph.size())
+            .into_par_iter()
+            .map(|start_idx| search::paths_of_length_from(&csr, start_idx, length, max_chains))
+            .collect();
+
+        let count: u64 = found.iter().map(|f| f.count).sum();
+        let chains: Vec<Vec<String>> = found
+            .into_iter()
+            .flat_map(|f| f.paths)
+            .take(max_chains)
+            .map(|path| path.iter().map(|idx| graph.word(*idx).to_string()).collect())
+            .collect();
+
+        let filename = dirs.all_longest_chains_file(graph.word_length());
+        write_chain_list_file(&filename, count, &chains).expect("Unable to write chain list file");
+
+        println!(
+            "There are {} chains of {} words for word length {}, and {} of them are listed in {:?}",
+            count,
+            length,
+            graph.word_length(),
+            chains.len(),
+            filename
+        );
+
+        if !summary.is_proven_optimal() {
+            println!("These chains are not proven to be the longest, so there may be longer ones");
+        }
+    }
+}
+
+/// Calculates the longest loop in each graph, then summarises the loops of
+/// every word length.
+fn calculate_loops(dirs: &RelativeDirectories, graphs: &[Graph]) {
+    for graph in graphs {
+        calculate_longest_loop(dirs, graph);
+    }
+
+    let summaries: Vec<_> = ALL_WORD_LENGTHS
+        .filter_map(|word_length| summarise_loops(dirs, word_length))
+        .collect();
+
+    write_longest_loops_file(dirs, &summaries);
+}
+
+/// Calculates the longest loop in a graph and writes it to
+/// 'output\loopsNN\00_longest_loop.txt', unless that file already holds a
+/// loop which is proven to be the longest.
+fn calculate_longest_loop(dirs: &RelativeDirectories, graph: &Graph) {
+    let filename = dirs.longest_loop_file(graph.word_length());
+    let loop_solver = LoopSolver::new(graph);
+    let upper_bound = loop_solver.upper_bound();
+
+    // Only a loop of the current graph is worth keeping.
+    let previous = read_chain_file(&filename).ok().and_then(|chain_file| {
+        let previous: Option<Vec<usize>> = chain_file
+            .chain
+            .iter()
+            .map(|w| graph.find(w))
+            .collect();
+        previous
+            .filter(|previous| is_loop(graph, previous))
+            .map(|previous| (previous, chain_file.is_proven_longest()))
+    });
+
+    let previous = match previous {
+        Some((previous, true)) => {
+            println!(
+                "The longest loop of word length {} is already known to have {} words",
+                graph.word_length(),
+                previous.len()
+            );
+            return;
+        }
+        Some((previous, false)) => previous,
+        None => Vec::new(),
+    };
+
+    println!(
+        "Calculating the longest loop for the graph of word length {} (upper bound on loop length is {})",
+        graph.word_length(),
+        upper_bound
+    );
+
+    let incumbent = Incumbent::new(previous.len());
+    let mut longest = loop_solver.longest_loop(&incumbent);
+    if longest.len() <= previous.len() {
+        longest = previous;
+    }
+
+    if longest.is_empty() {
+        println!("There are no loops of word length {}", graph.word_length());
+        return;
+    }
+
+    // The search was exhaustive, so nothing is longer.
+    let chain_file = ChainFile {
+        chain: longest.iter().map(|idx| graph.word(*idx).to_string()).collect(),
+        provenance: Some(Provenance::new(true, longest.len(), upper_bound).proven_by(Proof::WholeGraph)),
+    };
+
+    std::fs::create_dir_all(dirs.loops_directory(graph.word_length())).unwrap();
+    write_chain_file(&filename, &chain_file).expect("Unable to write loop file");
+
+    println!(
+        "The longest loop of word length {} has {} words, through {}",
+        graph.word_length(),
+        longest.len(),
+        chain_file.chain.join(" ")
+    );
+}
+
+/// Calculates the longest chain starting at `word`, returned as a list
+/// of vertex indices. The search is checkpointed to 'output\chainsNN\{word}.checkpoint'.
+fn calculate_longest_path_for_word(
+    dirs: &RelativeDirectories,
+    graph: &Graph,
+    csr: &CsrGraph,
+    word: &str,
+    upper_bound: usize,
+    incumbent: Option<&Incumbent>,
+) -> Vec<usize> {
+    let start_idx = graph.get_index_for_word(word);
+    let mut checkpoint_file = dirs.chains_directory(graph.word_length());
+    checkpoint_file.push(format!("{}.checkpoint", word));
+    search::longest_path_from(csr, start_idx, upper_bound, incumbent, Some(&checkpoint_file))
+}
+
+/// Reads the chain files of the words already completed, so that later
+/// searches can build on them. Also returns whether every one of them is
+/// proven to be the longest from its start word.
+fn read_known_chains(
+    dirs: &RelativeDirectories,
+    graph: &Graph,
+    completed_words: &[String],
+) -> (KnownChains, bool) {
+    let known_chains = KnownChains::new(graph.size());
+    let mut all_proven = true;
+
+    for word in completed_words {
+        let chain: Option<Vec<usize>> = match read_chain_file(&chain_filename(dirs, word)) {
+            Ok(chain_file) => {
+                all_proven &= chain_file.is_longest_from_start_word();
+                chain_file.chain.iter().map(|w| graph.find(w)).collect()
+            }
+            Err(_) => None,
+        };
+
+        // A chain calculated for a different version of the graph is of no use.
+        match chain {
+            Some(chain) if is_simple_path(graph, &chain) => known_chains.offer(&chain),
+            _ => all_proven = false,
+        }
+    }
+
+    (known_chains, all_proven)
+}
+
+/// Returns true if `path` is a loop in the graph: a chain of at least 3 words
+/// whose last word is adjacent to its first.
+fn is_loop(graph: &Graph, path: &[usize]) -> bool {
+    path.len() >= 3
+        && is_simple_path(graph, path)
+        && graph.is_adjacent(path[path.len() - 1], path[0])
+}
+
+/// Returns true if `path` is a chain in the graph which does not use any word twice.
+fn is_simple_path(graph: &Graph, path: &[usize]) -> bool {
+    let mut seen = HashSet::new();
+    path.iter().all(|&v| seen.insert(v))
+        && path
+            .windows(2)
+            .all(|w| graph.is_adjacent(w[0], w[1]))
+}
+
+/// Writes the best chain known from a word to its output file. `exact` says
+/// whether the search from the word was exact.
+fn write_known_chain(
+    dirs: &RelativeDirectories,
+    graph: &Graph,
+    known_chains: &KnownChains,
+    start_idx: usize,
+    exact: bool,
+    upper_bound: usize,
+) {
+    let path = known_chains.chain_from(start_idx).unwrap_or_else(|| vec![start_idx]);
+    write_chain(dirs, graph, &path, Provenance::new(exact, path.len(), upper_bound));
+}
+
+/// Writes the longest chain found in a graph to its output file, unless it is
+/// a single word. If `proof` is given then no chain can be longer, and that is
+/// recorded as the reason.
+fn write_longest_chain(
+    dirs: &RelativeDirectories,
+    graph: &Graph,
+    path: &[usize],
+    proof: Option<Proof>,
+    exact: bool,
+    upper_bound: usize,
+) {
+    if path.len() <= 1 {
+        return;
+    }
+
+    let mut provenance = Provenance::new(exact, path.len(), upper_bound);
+    if let Some(proof) = proof {
+        provenance = provenance.proven_by(proof);
+    }
+
+    write_chain(dirs, graph, path, provenance);
+}
+
+/// Writes a chain, given as a list of vertex indices, to its output file.
+fn write_chain(dirs: &RelativeDirectories, graph: &Graph, path: &[usize], provenance: Provenance) {
+    let chain_file = ChainFile {
+        chain: path.iter().map(|idx| graph.word(*idx).to_string()).collect(),
+        provenance: Some(provenance),
+    };
+    write_path_output_file(dirs, &chain_file);
+}
+
+/// Writes a chain to the chain file of its first word. A word with no
+/// neighbours gets a chain of just itself, so that it counts as done.
+fn write_path_output_file(dirs: &RelativeDirectories, chain_file: &ChainFile) {
+    let path = &chain_file.chain;
+    let filename = chain_filename(dirs, &path[0]);
+    std::fs::create_dir_all(filename.parent().unwrap()).unwrap();
+
+    write_chain_file(&filename, chain_file).expect("Unable to write chain file");
 
     println!("Wrote a chain of length {} to {:?}", path.len(),  filename);
 }
+
+/// Returns the name of the chain file for a start word, 'output\chainsNN\{word}.txt'.
+fn chain_filename(dirs: &RelativeDirectories, word: &str) -> PathBuf {
+    let mut filename = dirs.chains_directory(word.len());
+    filename.push(format!("{}.txt", word));
+    filename
+}
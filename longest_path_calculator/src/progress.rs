@@ -0,0 +1,258 @@
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Counts of the work done by the exhaustive searches. These are shared by
+/// every search in the process, which add their own counts in batches so
+/// that the threads do not contend for them.
+#[derive(Debug, Default)]
+pub struct SearchCounters {
+    /// The number of paths extended by a vertex.
+    nodes: AtomicU64,
+    /// The number of branches cut because even every unvisited vertex would
+    /// not have been enough to beat the best path.
+    pruned_by_size: AtomicU64,
+    /// The number of branches cut because the unvisited vertices reachable
+    /// from the tip would not have been enough.
+    pruned_by_reachability: AtomicU64,
+    /// The number of branches cut because none of the vertices where a path
+    /// has to end could still be reached.
+    pruned_by_end: AtomicU64,
+    /// The length of the longest chain that any search has found.
+    best: AtomicUsize,
+}
+
+/// The counters which the searches add to.
+pub static SEARCH_COUNTERS: SearchCounters = SearchCounters::new();
+
+/// The reasons why a search abandons a branch.
+#[derive(Debug, Clone, Copy)]
+pub enum PruneReason {
+    Size,
+    Reachability,
+    End,
+}
+
+/// The counts of one search which are yet to be added to `SEARCH_COUNTERS`.
+#[derive(Debug, Default)]
+pub struct LocalCounts {
+    pub nodes: u64,
+    pruned: [u64; 3],
+}
+
+impl LocalCounts {
+    pub fn prune(&mut self, reason: PruneReason) {
+        self.pruned[reason as usize] += 1;
+    }
+}
+
+impl SearchCounters {
+    const fn new() -> Self {
+        Self {
+            nodes: AtomicU64::new(0),
+            pruned_by_size: AtomicU64::new(0),
+            pruned_by_reachability: AtomicU64::new(0),
+            pruned_by_end: AtomicU64::new(0),
+            best: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds the counts of a search to the totals, and resets them.
+    pub fn add(&self, counts: &mut LocalCounts) {
+        self.nodes.fetch_add(counts.nodes, Ordering::Relaxed);
+        self.pruned_by_size.fetch_add(counts.pruned[PruneReason::Size as usize], Ordering::Relaxed);
+        self.pruned_by_reachability
+            .fetch_add(counts.pruned[PruneReason::Reachability as usize], Ordering::Relaxed);
+        self.pruned_by_end.fetch_add(counts.pruned[PruneReason::End as usize], Ordering::Relaxed);
+        *counts = LocalCounts::default();
+    }
+
+    /// Records that a chain of `length` words has been found.
+    pub fn offer_best(&self, length: usize) {
+        self.best.fetch_max(length, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            nodes: self.nodes.load(Ordering::Relaxed),
+            pruned_by_size: self.pruned_by_size.load(Ordering::Relaxed),
+            pruned_by_reachability: self.pruned_by_reachability.load(Ordering::Relaxed),
+            pruned_by_end: self.pruned_by_end.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The search counters at one moment.
+#[derive(Debug, Default, Clone, Copy)]
+struct Snapshot {
+    nodes: u64,
+    pruned_by_size: u64,
+    pruned_by_reachability: u64,
+    pruned_by_end: u64,
+}
+
+impl Snapshot {
+    fn since(&self, earlier: &Snapshot) -> Snapshot {
+        Snapshot {
+            nodes: self.nodes - earlier.nodes,
+            pruned_by_size: self.pruned_by_size - earlier.pruned_by_size,
+            pruned_by_reachability: self.pruned_by_reachability - earlier.pruned_by_reachability,
+            pruned_by_end: self.pruned_by_end - earlier.pruned_by_end,
+        }
+    }
+}
+
+/// Tracks the progress of the calculation for one word length, and reports
+/// it periodically, both on the console and as a row appended to
+/// 'output\progress_NN.csv'.
+pub struct Progress {
+    word_length: usize,
+    num_words: usize,
+    words_done: AtomicUsize,
+    filename: PathBuf,
+    started: Instant,
+    /// The counters when the calculation started, as they also include the
+    /// work done for other word lengths.
+    initial: Snapshot,
+}
+
+impl Progress {
+    /// Starts tracking the calculation of chains from `num_words` start
+    /// words, of which the longest chain already known has `best` words.
+    pub fn new(word_length: usize, num_words: usize, best: usize, filename: PathBuf) -> Self {
+        SEARCH_COUNTERS.best.store(best, Ordering::Relaxed);
+
+        Self {
+            word_length,
+            num_words,
+            words_done: AtomicUsize::new(0),
+            filename,
+            started: Instant::now(),
+            initial: SEARCH_COUNTERS.snapshot(),
+        }
+    }
+
+    /// Records that the calculation for one start word is complete, and that
+    /// its chain has `length` words.
+    pub fn word_done(&self, length: usize) {
+        self.words_done.fetch_add(1, Ordering::Relaxed);
+        SEARCH_COUNTERS.offer_best(length);
+    }
+
+    /// Runs `calculate`, reporting progress every `interval` until it
+    /// returns. No reports are made if `interval` is zero.
+    pub fn report_while<F: FnOnce()>(&self, interval: Duration, calculate: F) {
+        if interval.is_zero() {
+            calculate();
+            return;
+        }
+
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut previous = (Instant::now(), self.initial);
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    previous = self.report(previous);
+                }
+            });
+
+            calculate();
+            drop(stop);
+        });
+    }
+
+    /// Prints a progress report and appends it to the progress file. The
+    /// rates are calculated since the `previous` report, and the time and
+    /// counters of this report are returned for use by the next one.
+    fn report(&self, previous: (Instant, Snapshot)) -> (Instant, Snapshot) {
+        let now = Instant::now();
+        let current = SEARCH_COUNTERS.snapshot();
+        let total = current.since(&self.initial);
+        let recent = current.since(&previous.1);
+        let nodes_per_second = recent.nodes as f64 / (now - previous.0).as_secs_f64();
+
+        let elapsed = now - self.started;
+        let words_done = self.words_done.load(Ordering::Relaxed);
+        let words_remaining = self.num_words - words_done;
+        let best = SEARCH_COUNTERS.best.load(Ordering::Relaxed);
+
+        // Assume that the remaining words take as long as the ones done.
+        let eta = if words_done == 0 {
+            None
+        } else {
+            Some(elapsed.mul_f64(words_remaining as f64 / words_done as f64))
+        };
+
+        println!(
+            "Word length {}: {} of {} words done, best chain {} words, {:.0} nodes/s, pruned {} by size, {} by reachability, {} by end, ETA {}",
+            self.word_length,
+            words_done,
+            self.num_words,
+            best,
+            nodes_per_second,
+            total.pruned_by_size,
+            total.pruned_by_reachability,
+            total.pruned_by_end,
+            eta.map_or_else(|| "unknown".to_string(), format_duration)
+        );
+
+        let row = [
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs().to_string(),
+            self.word_length.to_string(),
+            elapsed.as_secs().to_string(),
+            words_done.to_string(),
+            words_remaining.to_string(),
+            best.to_string(),
+            total.nodes.to_string(),
+            format!("{:.0}", nodes_per_second),
+            total.pruned_by_size.to_string(),
+            total.pruned_by_reachability.to_string(),
+            total.pruned_by_end.to_string(),
+            eta.map_or_else(String::new, |eta| eta.as_secs().to_string()),
+        ];
+
+        if let Err(e) = self.append_row(&row) {
+            eprintln!("Unable to write progress file {:?}: {}", self.filename, e);
+        }
+
+        (now, current)
+    }
+
+    /// Appends a row to the progress file, writing the header first if the
+    /// file is new.
+    fn append_row(&self, row: &[String]) -> csv::Result<()> {
+        let is_new = !self.filename.exists();
+        let file = OpenOptions::new().create(true).append(true).open(&self.filename)?;
+        let mut writer = csv::Writer::from_writer(file);
+
+        if is_new {
+            writer.write_record([
+                "Timestamp",
+                "Len",
+                "ElapsedSeconds",
+                "WordsDone",
+                "WordsRemaining",
+                "BestChainLength",
+                "Nodes",
+                "NodesPerSecond",
+                "PrunedBySize",
+                "PrunedByReachability",
+                "PrunedByEnd",
+                "EtaSeconds",
+            ])?;
+        }
+
+        writer.write_record(row)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Formats a duration as hours, minutes and seconds, e.g. '2h 05m 09s'.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}h {:02}m {:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
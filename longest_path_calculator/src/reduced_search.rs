@@ -0,0 +1,198 @@
+use graph::{CsrGraph, Graph, ReducedGraph, VertexLocation};
+
+use crate::search::{self, Adjacency, Incumbent};
+
+/// Calculates longest paths by searching a `ReducedGraph` rather than the
+/// original graph, then expanding the result back into words.
+///
+/// Starting at a word that was contracted into a run takes a little care. The
+/// path must first walk to one end of the run and leave it there, but may come
+/// back later and finish by walking into the unused part of the run from the
+/// other end.
+pub struct ReducedSolver {
+    reduced: ReducedGraph,
+    /// The adjacency lists of `reduced`, in the compact form the search uses.
+    csr: CsrGraph,
+    weights: Vec<usize>,
+    upper_bound: usize,
+}
+
+impl ReducedSolver {
+    pub fn new(graph: &Graph, upper_bound: usize) -> Self {
+        let reduced = graph.reduce();
+        let weights = reduced.vertices.iter().map(|v| v.weight()).collect();
+
+        Self {
+            csr: reduced.to_csr(),
+            reduced,
+            weights,
+            upper_bound,
+        }
+    }
+
+    /// Returns the number of vertices in the reduced graph.
+    pub fn reduced_size(&self) -> usize {
+        self.reduced.size()
+    }
+
+    /// Returns the longest simple path starting at `start_idx`, as indexes
+    /// into the vertices of the original graph. If `incumbent` is given then,
+    /// as with `search::longest_path_from`, the result may be shorter than it.
+    pub fn longest_path_from(&self, start_idx: usize, incumbent: Option<&Incumbent>) -> Vec<usize> {
+        match self.reduced.locations[start_idx] {
+            VertexLocation::Reduced { vertex, position } => {
+                let reduced_vertex = &self.reduced.vertices[vertex];
+
+                if reduced_vertex.run.len() == 1 {
+                    // There is nothing to come back for, so just search from here.
+                    let path = search::heaviest_path_from(
+                        &self.csr,
+                        vertex,
+                        &self.weights,
+                        self.upper_bound,
+                        incumbent,
+                        0,
+                    );
+                    return self.reduced.expand_path(&path);
+                }
+
+                let run = &reduced_vertex.run;
+                let towards_first = self.leave_run(
+                    incumbent,
+                    vertex,
+                    run[..=position].iter().rev().copied().collect(),
+                    reduced_vertex.first_neighbour,
+                    run[position + 1..].iter().rev().copied().collect(),
+                );
+
+                let towards_last = self.leave_run(
+                    incumbent,
+                    vertex,
+                    run[position..].to_vec(),
+                    reduced_vertex.last_neighbour,
+                    run[..position].to_vec(),
+                );
+
+                if towards_first.len() >= towards_last.len() {
+                    towards_first
+                } else {
+                    towards_last
+                }
+            }
+            VertexLocation::Trimmed { pendant, position } => {
+                let pendant = &self.reduced.trimmed[pendant];
+
+                let towards_leaf = pendant.run[position..].to_vec();
+
+                let mut towards_attachment: Vec<usize> =
+                    pendant.run[..=position].iter().rev().copied().collect();
+                let path = search::heaviest_path_from(
+                    &self.csr,
+                    pendant.attached_to,
+                    &self.weights,
+                    self.upper_bound.saturating_sub(towards_attachment.len()),
+                    incumbent,
+                    towards_attachment.len(),
+                );
+                towards_attachment.extend(self.reduced.expand_path(&path));
+
+                if towards_attachment.len() >= towards_leaf.len() {
+                    towards_attachment
+                } else {
+                    towards_leaf
+                }
+            }
+        }
+    }
+
+    /// Calculates the longest path which begins with `prefix`, a walk from the
+    /// start word to one end of the run `run_vertex`, then continues at the
+    /// `exit` vertex adjacent to that end. The path may come back into the run
+    /// from its other end, in which case it finishes with `remainder`, the
+    /// unused words of the run in the order they would be walked.
+    fn leave_run(
+        &self,
+        incumbent: Option<&Incumbent>,
+        run_vertex: usize,
+        prefix: Vec<usize>,
+        exit: Option<usize>,
+        remainder: Vec<usize>,
+    ) -> Vec<usize> {
+        let exit = match exit {
+            Some(exit) => exit,
+            None => return prefix,
+        };
+
+        // The run can no longer be entered from the end we are leaving by,
+        // and is only worth the words that remain unused.
+        let graph = WithoutEdge::new(&self.csr, exit, run_vertex);
+        let mut weights = self.weights.clone();
+        weights[run_vertex] = remainder.len();
+
+        let path = search::heaviest_path_from(
+            &graph,
+            exit,
+            &weights,
+            self.upper_bound.saturating_sub(prefix.len()),
+            incumbent,
+            prefix.len(),
+        );
+
+        let mut full_path = prefix;
+        match path.split_last() {
+            Some((&last, rest)) if last == run_vertex => {
+                full_path.extend(self.reduced.expand_path(rest));
+                full_path.extend(remainder);
+            }
+            _ => full_path.extend(self.reduced.expand_path(&path)),
+        }
+
+        full_path
+    }
+}
+
+/// A view of a graph with a single edge removed.
+struct WithoutEdge<'a, G> {
+    graph: &'a G,
+    a: usize,
+    b: usize,
+    a_neighbours: Vec<u32>,
+    b_neighbours: Vec<u32>,
+}
+
+impl<'a, G: Adjacency> WithoutEdge<'a, G> {
+    fn new(graph: &'a G, a: usize, b: usize) -> Self {
+        let without = |v: usize, other: usize| {
+            graph
+                .neighbours(v)
+                .iter()
+                .copied()
+                .filter(|&n| n as usize != other)
+                .collect()
+        };
+
+        Self {
+            graph,
+            a,
+            b,
+            a_neighbours: without(a, b),
+            b_neighbours: without(b, a),
+        }
+    }
+}
+
+impl<'a, G: Adjacency> Adjacency for WithoutEdge<'a, G> {
+    fn size(&self) -> usize {
+        self.graph.size()
+    }
+
+    fn neighbours(&self, vertex_index: usize) -> &[u32] {
+        if vertex_index == self.a {
+            &self.a_neighbours
+        } else if vertex_index == self.b {
+            &self.b_neighbours
+        } else {
+            self.graph.neighbours(vertex_index)
+        }
+    }
+}
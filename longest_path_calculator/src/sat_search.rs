@@ -0,0 +1,458 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use graph::Graph;
+use varisat::dimacs::write_dimacs;
+use varisat::{CnfFormula, ExtendFormula, Lit, ProofFormat, Solver};
+
+use crate::search::Incumbent;
+
+/// Calculates longest paths exactly with a SAT solver.
+///
+/// The question "is there a chain of at least `k` words starting at `s`?" is
+/// encoded as a boolean formula and given to varisat, a CDCL SAT solver written
+/// in Rust. A satisfying assignment is a chain, and when the formula becomes
+/// unsatisfiable the solver has proved that no longer chain exists. `k` starts
+/// just above the best chain known and goes up until that happens.
+///
+/// The formula selects a set of vertices and, for each selected vertex other than
+/// the start, the arc along which the path enters it. Each vertex has at most one
+/// arc in and one arc out, and a totalizer counts the selected vertices. This
+/// allows the selected vertices to form cycles which are not connected to the
+/// path, so whenever the solver returns such a cycle we add clauses saying that
+/// if any vertex in it is used then the path must enter it from outside, and
+/// solve again. These clauses hold for every chain, so they are kept in the
+/// formula and shared by the searches from later start words.
+///
+/// If a proof directory is given, each proof is also written out for anyone to
+/// check: '{word}.cnf' is the formula, in DIMACS form, for a chain from `word`
+/// one longer than the longest found, and '{word}.drat' is a DRAT proof that it
+/// is unsatisfiable, which can be checked with a tool such as drat-trim.
+pub struct SatSolver<'a> {
+    graph: &'a Graph,
+    upper_bound: usize,
+    /// Encodings not currently in use. A search takes one, or builds one if
+    /// there are none, and puts it back when it has finished, so that the cycles
+    /// ruled out in one search stay ruled out in the next.
+    encodings: Mutex<Vec<PathEncoding>>,
+    proof_directory: Option<PathBuf>,
+}
+
+impl<'a> SatSolver<'a> {
+    pub fn new(graph: &'a Graph, upper_bound: usize, proof_directory: Option<PathBuf>) -> Self {
+        if let Some(dir) = &proof_directory {
+            fs::create_dir_all(dir).unwrap();
+        }
+
+        Self {
+            graph,
+            upper_bound,
+            encodings: Mutex::new(Vec::new()),
+            proof_directory,
+        }
+    }
+
+    /// Returns the longest simple path starting at `start_idx`. If `incumbent`
+    /// is given then, as with `search::longest_path_from`, the result may be
+    /// shorter than it.
+    pub fn longest_path_from(&self, start_idx: usize, incumbent: Option<&Incumbent>) -> Vec<usize> {
+        let name = self.graph.word(start_idx);
+        self.solve(Some(start_idx), incumbent, name)
+    }
+
+    /// Returns the longest simple path in the whole graph, found by leaving the
+    /// start vertex open rather than trying each one in turn. If `incumbent` is
+    /// given, the result may be shorter than it, and may be empty.
+    pub fn longest_path(&self, incumbent: Option<&Incumbent>) -> Vec<usize> {
+        self.solve(None, incumbent, "00_longest_path")
+    }
+
+    /// Solves for the longest path, with any proof written to files named after `name`.
+    fn solve(
+        &self,
+        start_idx: Option<usize>,
+        incumbent: Option<&Incumbent>,
+        name: &str,
+    ) -> Vec<usize> {
+        let encoding = self.encodings.lock().unwrap().pop();
+        let mut encoding =
+            encoding.unwrap_or_else(|| PathEncoding::new(self.graph, self.upper_bound));
+
+        let (path, disproved_length) = encoding.longest_path(start_idx, incumbent);
+
+        if let (Some(dir), Some(length)) = (&self.proof_directory, disproved_length) {
+            if let Err(e) = encoding.write_proof(dir, name, start_idx, length) {
+                eprintln!("Unable to write the proof for {}: {}", name, e);
+            }
+        }
+
+        self.encodings.lock().unwrap().push(encoding);
+        path
+    }
+}
+
+/// The formula for the chains in a graph. A new solver is made for each search,
+/// because varisat's solver cannot be moved between threads.
+struct PathEncoding {
+    formula: CnfFormula,
+    /// For each vertex, true if it is on the path.
+    selected: Vec<Lit>,
+    /// For each vertex, true if the path starts there.
+    is_start: Vec<Lit>,
+    /// For each vertex, the arcs into it as (from, literal).
+    in_arcs: Vec<Vec<(usize, Lit)>>,
+    /// For each vertex, the arcs out of it as (to, literal).
+    out_arcs: Vec<Vec<(usize, Lit)>>,
+    /// `at_least[k - 1]` can only be true if at least `k` vertices are selected.
+    at_least: Vec<Lit>,
+}
+
+impl PathEncoding {
+    fn new(graph: &Graph, upper_bound: usize) -> Self {
+        let n = graph.size();
+        let mut formula = CnfFormula::new();
+        let selected: Vec<Lit> = (0..n).map(|_| formula.new_lit()).collect();
+        let is_start: Vec<Lit> = (0..n).map(|_| formula.new_lit()).collect();
+
+        let mut in_arcs = vec![Vec::new(); n];
+        let mut out_arcs = vec![Vec::new(); n];
+        for (u, out_arcs) in out_arcs.iter_mut().enumerate() {
+            for v in graph.neighbours(u) {
+                let arc = formula.new_lit();
+                out_arcs.push((v, arc));
+                in_arcs[v].push((u, arc));
+            }
+        }
+
+        for v in 0..n {
+            // Only selected vertices can be joined by arcs.
+            for &(u, arc) in &in_arcs[v] {
+                formula.add_clause(&[!arc, selected[u]]);
+                formula.add_clause(&[!arc, selected[v]]);
+                formula.add_clause(&[!arc, !is_start[v]]);
+            }
+
+            // The path is entered along a single arc, except at its start.
+            let mut clause = vec![!selected[v], is_start[v]];
+            clause.extend(in_arcs[v].iter().map(|&(_, arc)| arc));
+            formula.add_clause(&clause);
+            formula.add_clause(&[!is_start[v], selected[v]]);
+
+            let arcs: Vec<Lit> = in_arcs[v].iter().map(|&(_, arc)| arc).collect();
+            add_at_most_one(&mut formula, &arcs);
+            let arcs: Vec<Lit> = out_arcs[v].iter().map(|&(_, arc)| arc).collect();
+            add_at_most_one(&mut formula, &arcs);
+
+            // An edge can't be used in both directions.
+            for &(u, arc) in &out_arcs[v] {
+                if u > v {
+                    let reverse = in_arcs[v].iter().find(|&&(w, _)| w == u).unwrap().1;
+                    formula.add_clause(&[!arc, !reverse]);
+                }
+            }
+        }
+
+        add_at_most_one(&mut formula, &is_start);
+        let at_least = add_totalizer(&mut formula, &selected, upper_bound.min(n));
+
+        Self {
+            formula,
+            selected,
+            is_start,
+            in_arcs,
+            out_arcs,
+            at_least,
+        }
+    }
+
+    /// Finds the longest path from `start_idx`, or from anywhere if it is None,
+    /// unless nothing beats `incumbent`. Also returns the length that the solver
+    /// proved impossible, unless it stopped at the upper bound instead.
+    fn longest_path(
+        &mut self,
+        start_idx: Option<usize>,
+        incumbent: Option<&Incumbent>,
+    ) -> (Vec<usize>, Option<usize>) {
+        let mut solver = Solver::new();
+        solver.add_formula(&self.formula);
+        let mut best: Vec<usize> = start_idx.into_iter().collect();
+
+        loop {
+            let to_beat = best.len().max(incumbent.map_or(0, |i| i.get()));
+            if to_beat >= self.at_least.len() {
+                return (best, None);
+            }
+
+            let mut assumptions = vec![self.at_least[to_beat]];
+            assumptions.extend(start_idx.map(|s| self.is_start[s]));
+            solver.assume(&assumptions);
+            if !solver.solve().expect("The SAT solver failed") {
+                return (best, Some(to_beat + 1));
+            }
+
+            let model = solver.model().unwrap();
+            let mut values = vec![false; self.formula.var_count()];
+            for lit in model {
+                values[lit.index()] = lit.is_positive();
+            }
+
+            // If the path is too short, the rest of the selected vertices
+            // form cycles that need to be ruled out.
+            let path = self.path_from_start(&values);
+            if path.len() <= to_beat {
+                for clause in self.cut_cycles(&path, &values) {
+                    solver.add_clause(&clause);
+                    self.formula.add_clause(&clause);
+                }
+            }
+
+            if path.len() > best.len() {
+                if let Some(incumbent) = incumbent {
+                    incumbent.offer(path.len());
+                }
+                best = path;
+            }
+        }
+    }
+
+    /// Follows the selected arcs from the selected start vertex, if there is one.
+    fn path_from_start(&self, values: &[bool]) -> Vec<usize> {
+        let start_idx = match self.is_start.iter().position(|&lit| values[lit.index()]) {
+            Some(start_idx) => start_idx,
+            None => return Vec::new(),
+        };
+
+        let mut path = vec![start_idx];
+        let mut tip = start_idx;
+
+        while let Some(&(next, _)) = self.out_arcs[tip]
+            .iter()
+            .find(|&&(_, arc)| values[arc.index()])
+        {
+            path.push(next);
+            tip = next;
+        }
+
+        path
+    }
+
+    /// Returns clauses which rule out the groups of selected vertices that are
+    /// not connected to `path`. If a chain uses any vertex in such a group, it
+    /// either starts in the group or enters it along an arc from outside.
+    fn cut_cycles(&self, path: &[usize], values: &[bool]) -> Vec<Vec<Lit>> {
+        let mut clauses = Vec::new();
+        let mut seen: HashSet<usize> = path.iter().copied().collect();
+        let is_selected = |v: usize| values[self.selected[v].index()];
+
+        for v in 0..self.selected.len() {
+            if !is_selected(v) || seen.contains(&v) {
+                continue;
+            }
+
+            // Collect the vertices joined to v by selected arcs.
+            let mut group = vec![v];
+            seen.insert(v);
+            let mut i = 0;
+            while i < group.len() {
+                let u = group[i];
+                i += 1;
+                let arcs = self.in_arcs[u].iter().chain(&self.out_arcs[u]);
+                for &(w, arc) in arcs {
+                    if values[arc.index()] && seen.insert(w) {
+                        group.push(w);
+                    }
+                }
+            }
+
+            let members: HashSet<usize> = group.iter().copied().collect();
+            let mut boundary: Vec<Lit> = group.iter().map(|&u| self.is_start[u]).collect();
+            for &u in &group {
+                boundary.extend(
+                    self.in_arcs[u]
+                        .iter()
+                        .filter(|&&(w, _)| !members.contains(&w))
+                        .map(|&(_, arc)| arc),
+                );
+            }
+
+            for &u in &group {
+                let mut clause = boundary.clone();
+                clause.push(!self.selected[u]);
+                clauses.push(clause);
+            }
+        }
+
+        clauses
+    }
+
+    /// Writes the formula stating that there is a chain of `length` words, from
+    /// `start_idx` if given, and a DRAT proof that it is unsatisfiable.
+    fn write_proof(
+        &self,
+        dir: &Path,
+        name: &str,
+        start_idx: Option<usize>,
+        length: usize,
+    ) -> io::Result<()> {
+        let mut formula = CnfFormula::new();
+        formula.set_var_count(self.formula.var_count());
+        for clause in self.formula.iter() {
+            formula.add_clause(clause);
+        }
+        if let Some(start_idx) = start_idx {
+            formula.add_clause(&[self.is_start[start_idx]]);
+        }
+        formula.add_clause(&[self.at_least[length - 1]]);
+
+        let mut writer = BufWriter::new(File::create(dir.join(format!("{}.cnf", name)))?);
+        match start_idx {
+            Some(_) => writeln!(
+                writer,
+                "c There is no chain of {} or more words starting at '{}'",
+                length, name
+            )?,
+            None => writeln!(writer, "c There is no chain of {} or more words", length)?,
+        }
+        write_dimacs(&mut writer, &formula)?;
+        writer.flush()?;
+
+        let proof = BufWriter::new(File::create(dir.join(format!("{}.drat", name)))?);
+        let mut solver = Solver::new();
+        solver.write_proof(proof, ProofFormat::BinaryDrat);
+        solver.add_formula(&formula);
+        let satisfiable = solver
+            .solve()
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        assert!(!satisfiable, "The proof formula should be unsatisfiable");
+        solver
+            .close_proof()
+            .map_err(|e| io::Error::other(e.to_string()))
+    }
+}
+
+/// Adds clauses allowing at most one of `lits` to be true. Short lists are
+/// done pairwise, and longer ones with the sequential encoding, which needs
+/// only a linear number of clauses.
+fn add_at_most_one(formula: &mut CnfFormula, lits: &[Lit]) {
+    if lits.len() <= 5 {
+        for (i, &a) in lits.iter().enumerate() {
+            for &b in &lits[i + 1..] {
+                formula.add_clause(&[!a, !b]);
+            }
+        }
+        return;
+    }
+
+    // some_before[i] is true if any of lits[..=i] is true.
+    let some_before: Vec<Lit> = (0..lits.len() - 1).map(|_| formula.new_lit()).collect();
+    for i in 0..lits.len() {
+        if i < some_before.len() {
+            formula.add_clause(&[!lits[i], some_before[i]]);
+        }
+        if i > 0 {
+            formula.add_clause(&[!lits[i], !some_before[i - 1]]);
+            if i < some_before.len() {
+                formula.add_clause(&[!some_before[i - 1], some_before[i]]);
+            }
+        }
+    }
+}
+
+/// Adds a totalizer which counts how many of `lits` are true, up to `max`.
+/// Returns the outputs, where output `k - 1` can only be true if at least `k`
+/// of the inputs are.
+fn add_totalizer(formula: &mut CnfFormula, lits: &[Lit], max: usize) -> Vec<Lit> {
+    if lits.len() == 1 {
+        return lits.to_vec();
+    }
+
+    let (left, right) = lits.split_at(lits.len() / 2);
+    let left = add_totalizer(formula, left, max);
+    let right = add_totalizer(formula, right, max);
+
+    let outputs: Vec<Lit> = (0..(left.len() + right.len()).min(max))
+        .map(|_| formula.new_lit())
+        .collect();
+
+    // If fewer than i + 1 of the left inputs and fewer than j + 1 of the right
+    // ones are true, then fewer than i + j + 1 of them are.
+    for i in 0..=left.len() {
+        for j in 0..=right.len() {
+            if i + j >= outputs.len() {
+                break;
+            }
+
+            let mut clause = vec![!outputs[i + j]];
+            if i < left.len() {
+                clause.push(left[i]);
+            }
+            if j < right.len() {
+                clause.push(right[j]);
+            }
+            formula.add_clause(&clause);
+        }
+    }
+
+    for k in 1..outputs.len() {
+        formula.add_clause(&[!outputs[k], outputs[k - 1]]);
+    }
+
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use varisat::dimacs::DimacsParser;
+
+    /// A chain of five words, and a separate chain of three.
+    fn test_graph() -> Graph {
+        Graph::from_words(["cold", "cord", "card", "ward", "warm", "tree", "free", "flee"]).unwrap()
+    }
+
+    /// Returns an empty directory of its own in the temporary directory.
+    fn test_directory(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sat_search_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Checks that the formula in `filename` is unsatisfiable, and that the
+    /// proof next to it is not empty.
+    fn assert_proof_written(filename: &Path, comment: &str) {
+        let cnf = fs::read_to_string(filename).unwrap();
+        assert!(cnf.starts_with(comment), "Unexpected proof comment in {:?}", cnf.lines().next());
+
+        let mut solver = Solver::new();
+        solver.add_formula(&DimacsParser::parse(cnf.as_bytes()).unwrap());
+        assert!(!solver.solve().unwrap());
+
+        let drat = fs::metadata(filename.with_extension("drat")).unwrap();
+        assert!(drat.len() > 0);
+    }
+
+    #[test]
+    fn proofs_are_written_when_the_bound_is_not_reached() {
+        let graph = test_graph();
+        let dir = test_directory("proofs");
+        let sat = SatSolver::new(&graph, graph.size(), Some(dir.clone()));
+
+        let cold = graph.get_index_for_word("cold");
+        assert_eq!(sat.longest_path_from(cold, None).len(), 5);
+        assert_proof_written(&dir.join("cold.cnf"), "c There is no chain of 6 or more words starting at 'cold'");
+
+        assert_eq!(sat.longest_path(None).len(), 5);
+        assert_proof_written(&dir.join("00_longest_path.cnf"), "c There is no chain of 6 or more words");
+
+        // Reaching the upper bound is proof enough, so nothing is written.
+        let sat = SatSolver::new(&graph, 3, Some(dir.clone()));
+        let tree = graph.get_index_for_word("tree");
+        assert_eq!(sat.longest_path_from(tree, None).len(), 3);
+        assert!(!dir.join("tree.cnf").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,962 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use graph::CsrGraph;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+use crate::bitset::BitSet;
+use crate::checkpoint::Checkpoint;
+use crate::progress::{LocalCounts, PruneReason, SEARCH_COUNTERS};
+
+/// How often an exhaustive search saves its state, if it has a checkpoint file.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The number of subtrees that the search from a start vertex is split into,
+/// where the graph allows. This is fixed, rather than depending on the number
+/// of threads, so that a checkpointed search can be resumed on any machine.
+const SUBTREES: usize = 64;
+
+/// The deepest that the search tree is split, however few subtrees that gives.
+const MAX_SPLIT_DEPTH: usize = 16;
+
+/// A graph that can be searched for long paths.
+pub trait Adjacency {
+    /// Returns the number of vertices in the graph.
+    fn size(&self) -> usize;
+
+    /// Returns the vertices adjacent to `vertex_index`.
+    fn neighbours(&self, vertex_index: usize) -> &[u32];
+}
+
+impl Adjacency for CsrGraph {
+    fn size(&self) -> usize {
+        CsrGraph::size(self)
+    }
+
+    fn neighbours(&self, vertex_index: usize) -> &[u32] {
+        CsrGraph::neighbours(self, vertex_index)
+    }
+}
+
+/// The length of the longest path found so far, shared between searches running
+/// in parallel. Each search only looks for paths longer than this, so a long
+/// path found by one search lets the others prune more.
+#[derive(Debug, Default)]
+pub struct Incumbent(AtomicUsize);
+
+impl Incumbent {
+    pub fn new(length: usize) -> Self {
+        Self(AtomicUsize::new(length))
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Records that a path of `length` has been found, if that is an improvement.
+    pub fn offer(&self, length: usize) {
+        self.0.fetch_max(length, Ordering::Relaxed);
+    }
+}
+
+/// Performs an exhaustive depth-first search with backtracking and returns the
+/// longest simple path that starts at `start_idx`. The path is a list of vertex
+/// indices, and each consecutive pair in it is a one-letter change, i.e. the
+/// second vertex is in the adjacency list of the first.
+///
+/// `upper_bound` is a cap on the length of any path in the graph, such as
+/// `WordLengthStatistics::largest_component_upper_bound`. The search stops as
+/// soon as it finds a path of that length, because it cannot be beaten.
+///
+/// If `incumbent` is given then only paths longer than it are searched for, and
+/// it is updated as longer ones are found. The result is still the longest path
+/// from `start_idx` unless that is no longer than the incumbent, in which case
+/// it may be shorter.
+///
+/// The search is split into subtrees which are searched in parallel, as
+/// described for `split_search`. If `checkpoint_file` is given the state of
+/// the search is saved periodically to files named after it, and if they
+/// already hold the state of an interrupted search for the same graph and
+/// start vertex then that search is resumed. The files are deleted when the
+/// search completes.
+pub fn longest_path_from(
+    graph: &CsrGraph,
+    start_idx: usize,
+    upper_bound: usize,
+    incumbent: Option<&Incumbent>,
+    checkpoint_file: Option<&Path>,
+) -> Vec<usize> {
+    let weights = vec![1; graph.size()];
+    let end_bonus = vec![0; graph.size()];
+    split_search(graph, start_idx, &weights, &end_bonus, upper_bound, incumbent, 0, checkpoint_file)
+}
+
+/// Like `longest_path_from`, but a path ending at vertex `v` scores
+/// `end_bonus[v]` on top of its length. This is used when a path that ends at
+/// `v` could be continued in another part of the graph which is not included in
+/// `graph`, and the bonus is the number of extra vertices that would provide.
+/// `upper_bound` and `incumbent` are compared with the score rather than with
+/// the length.
+pub fn longest_path_with_end_bonus(
+    graph: &CsrGraph,
+    start_idx: usize,
+    end_bonus: &[usize],
+    upper_bound: usize,
+    incumbent: Option<&Incumbent>,
+    checkpoint_file: Option<&Path>,
+) -> Vec<usize> {
+    let weights = vec![1; graph.size()];
+    split_search(graph, start_idx, &weights, end_bonus, upper_bound, incumbent, 0, checkpoint_file)
+}
+
+/// Like `longest_path_from`, but each vertex `v` contributes `weights[v]` to
+/// the score of a path rather than 1. This is used to search a `ReducedGraph`,
+/// where each vertex stands for a run of words. `upper_bound` is a cap on the
+/// score rather than on the length.
+///
+/// The path found is used as the continuation of a path of `prefix_length`
+/// words, so it is compared with `incumbent` after adding that length.
+pub fn heaviest_path_from<G: Adjacency + Sync>(
+    graph: &G,
+    start_idx: usize,
+    weights: &[usize],
+    upper_bound: usize,
+    incumbent: Option<&Incumbent>,
+    prefix_length: usize,
+) -> Vec<usize> {
+    let end_bonus = vec![0; graph.size()];
+    split_search(graph, start_idx, weights, &end_bonus, upper_bound, incumbent, prefix_length, None)
+}
+
+/// Searches for the best path from `start_idx` by splitting the search tree
+/// into subtrees, each rooted at a short path from the start vertex, and
+/// searching them in parallel. Rayon's work stealing then spreads a single
+/// hard search over every thread, including those which would otherwise sit
+/// idle once the searches from the other start vertices are done. The
+/// subtrees share an incumbent, so a long path found in one prunes the others.
+///
+/// If `checkpoint_file` is given each subtree is checkpointed to a file of its
+/// own named after it. A subtree which has been searched completely leaves a
+/// checkpoint saying so, until the whole search is complete.
+#[allow(clippy::too_many_arguments)]
+fn split_search<G: Adjacency + Sync>(
+    graph: &G,
+    start_idx: usize,
+    weights: &[usize],
+    end_bonus: &[usize],
+    upper_bound: usize,
+    incumbent: Option<&Incumbent>,
+    prefix_length: usize,
+    checkpoint_file: Option<&Path>,
+) -> Vec<usize> {
+    let own_incumbent = Incumbent::default();
+    let incumbent = incumbent.unwrap_or(&own_incumbent);
+
+    let subtrees = split_into_subtrees(graph, start_idx);
+    let checkpoint_files: Vec<Option<PathBuf>> = (0..subtrees.len())
+        .map(|i| checkpoint_file.map(|f| subtree_checkpoint_file(f, i)))
+        .collect();
+
+    let (_, best) = subtrees
+        .par_iter()
+        .zip(&checkpoint_files)
+        .map(|(root, checkpoint_file)| {
+            let mut search = PathSearch::from_root(graph, root, weights, end_bonus, upper_bound);
+            search.incumbent = Some(incumbent);
+            search.prefix_length = prefix_length;
+            search.checkpoint_file = checkpoint_file.as_deref();
+            search.restore_checkpoint();
+            search.run();
+            search.save_finished_checkpoint();
+            (search.best_score, search.best)
+        })
+        .reduce_with(|a, b| if b.0 > a.0 { b } else { a })
+        .unwrap_or_default();
+
+    for checkpoint_file in checkpoint_files.iter().flatten() {
+        Checkpoint::remove(checkpoint_file);
+    }
+
+    best
+}
+
+/// Splits the search tree from `start_idx` into subtrees, by extending the
+/// paths from the start vertex a level at a time until there are at least
+/// `SUBTREES` of them or they are `MAX_SPLIT_DEPTH` long. Each subtree is
+/// given by the path at its root. A path which cannot be extended is kept as
+/// a subtree of its own, as it may be the best path. The result only depends
+/// on the graph, so that checkpoints of the subtrees can be matched up again.
+fn split_into_subtrees<G: Adjacency>(graph: &G, start_idx: usize) -> Vec<Vec<usize>> {
+    let mut subtrees = vec![vec![start_idx]];
+
+    for _ in 0..MAX_SPLIT_DEPTH {
+        if subtrees.len() >= SUBTREES {
+            break;
+        }
+
+        let mut next_level = Vec::new();
+        for root in &subtrees {
+            let tip = *root.last().unwrap();
+            let num_before = next_level.len();
+
+            for &n in graph.neighbours(tip) {
+                if !root.contains(&(n as usize)) {
+                    let mut extended = root.clone();
+                    extended.push(n as usize);
+                    next_level.push(extended);
+                }
+            }
+
+            if next_level.len() == num_before {
+                next_level.push(root.clone());
+            }
+        }
+
+        if next_level.len() == subtrees.len() {
+            break;
+        }
+        subtrees = next_level;
+    }
+
+    subtrees
+}
+
+/// Returns the name of the checkpoint file for subtree `i` of a search whose
+/// checkpoint file is `checkpoint_file`, e.g. 'cold.7.checkpoint' for
+/// 'cold.checkpoint'.
+fn subtree_checkpoint_file(checkpoint_file: &Path, i: usize) -> PathBuf {
+    let stem = checkpoint_file.file_stem().unwrap_or_default().to_string_lossy();
+    checkpoint_file.with_file_name(format!("{}.{}.checkpoint", stem, i))
+}
+
+/// Like `longest_path_from`, but only counts paths which can be closed into a
+/// loop: paths of at least 3 vertices whose last vertex is adjacent to
+/// `start_idx`. Returns an empty path if there is no loop through `start_idx`.
+pub fn longest_loop_through(
+    graph: &CsrGraph,
+    start_idx: usize,
+    upper_bound: usize,
+    incumbent: Option<&Incumbent>,
+) -> Vec<usize> {
+    let weights = vec![1; graph.size()];
+    let end_bonus = vec![0; graph.size()];
+    let mut search = PathSearch::new(graph, start_idx, &weights, &end_bonus, upper_bound);
+    search.incumbent = incumbent;
+    search.required_end = Some(RequiredEnd::new(graph.size(), graph.neighbours(start_idx), 3));
+
+    // The start vertex on its own is not a loop.
+    search.best.clear();
+    search.best_score = 0;
+
+    search.run();
+    search.best
+}
+
+/// Like `longest_path_from`, but returns the longest simple path which ends at
+/// `end_idx`, or an empty path if `end_idx` cannot be reached.
+pub fn longest_path_to(graph: &CsrGraph, start_idx: usize, end_idx: usize) -> Vec<usize> {
+    if start_idx == end_idx {
+        return vec![start_idx];
+    }
+
+    let weights = vec![1; graph.size()];
+    let end_bonus = vec![0; graph.size()];
+    let mut search = PathSearch::new(graph, start_idx, &weights, &end_bonus, graph.size());
+    search.required_end = Some(RequiredEnd::new(graph.size(), &[end_idx as u32], 2));
+
+    // The start vertex on its own does not reach the end.
+    search.best.clear();
+    search.best_score = 0;
+
+    search.run();
+    search.best
+}
+
+/// The paths found by `paths_of_length_from`.
+#[derive(Debug, Default)]
+pub struct PathsOfLength {
+    /// The number of paths found.
+    pub count: u64,
+    /// The first of the paths found, up to the number asked for.
+    pub paths: Vec<Vec<usize>>,
+}
+
+/// Finds every simple path of exactly `length` vertices which starts at
+/// `start_idx` and ends at a vertex with a higher index. Searching from every
+/// vertex in turn then finds each path in the graph exactly once, rather than
+/// once from each end. Returns how many there are, and the first `max_paths`
+/// of them.
+///
+/// The search prunes any branch which cannot reach `length`, so this is about
+/// as fast as proving that there is no path longer than `length`.
+pub fn paths_of_length_from(
+    graph: &CsrGraph,
+    start_idx: usize,
+    length: usize,
+    max_paths: usize,
+) -> PathsOfLength {
+    if length < 2 {
+        return PathsOfLength::default();
+    }
+
+    let weights = vec![1; graph.size()];
+    let end_bonus = vec![0; graph.size()];
+    let mut search = PathSearch::new(graph, start_idx, &weights, &end_bonus, length);
+    search.enumeration = Some(Enumeration {
+        length,
+        max_paths,
+        found: PathsOfLength::default(),
+    });
+
+    // Only paths of the full length are of interest.
+    search.best.clear();
+    search.best_score = length - 1;
+
+    search.run();
+    search.enumeration.unwrap().found
+}
+
+/// The state of a search which is finding all the paths of one length.
+struct Enumeration {
+    length: usize,
+    max_paths: usize,
+    found: PathsOfLength,
+}
+
+/// The vertices at which a search requires its paths to end, for searches
+/// which are not looking for paths ending anywhere.
+struct RequiredEnd {
+    vertices: Vec<u32>,
+    contains: BitSet,
+    /// The fewest vertices a path must have to count.
+    min_length: usize,
+}
+
+impl RequiredEnd {
+    fn new(graph_size: usize, vertices: &[u32], min_length: usize) -> Self {
+        let mut contains = BitSet::new(graph_size);
+        for &v in vertices {
+            contains.insert(v as usize);
+        }
+
+        Self {
+            vertices: vertices.to_vec(),
+            contains,
+            min_length,
+        }
+    }
+}
+
+/// The state of an exhaustive search. The search is iterative rather than
+/// recursive because paths can be many thousands of vertices long, which
+/// would overflow the stack of a rayon worker thread.
+struct PathSearch<'a, G> {
+    graph: &'a G,
+    /// The path at the root of the search tree being explored, which is just
+    /// the start vertex unless the search is of one subtree.
+    root: Vec<usize>,
+    /// The path currently being explored. The first entry is the start vertex.
+    path: Vec<usize>,
+    /// The total weight of the vertices in `path`.
+    path_weight: usize,
+    /// For each entry in `path`, the position in that vertex's adjacency list
+    /// of the next neighbour to try.
+    cursors: Vec<usize>,
+    visited: BitSet,
+    weights: &'a [usize],
+    end_bonus: &'a [usize],
+    max_end_bonus: usize,
+    total_weight: usize,
+    best: Vec<usize>,
+    best_score: usize,
+    upper_bound: usize,
+    /// The best score found by any search, including this one, which is only
+    /// comparable with this search's scores after adding `prefix_length`.
+    incumbent: Option<&'a Incumbent>,
+    prefix_length: usize,
+    /// Where paths have to end, if they cannot end anywhere. For loops these
+    /// are the neighbours of the start vertex.
+    required_end: Option<RequiredEnd>,
+    /// If the search is finding all the paths of a given length rather than
+    /// the longest path, the ones found so far.
+    enumeration: Option<Enumeration>,
+    /// Scratch space for the reachability bound: the vertices that are either
+    /// visited or have been reached in the current bound calculation.
+    reached: BitSet,
+    queue: Vec<u32>,
+    checkpoint_file: Option<&'a Path>,
+    last_checkpoint: Instant,
+    steps: u64,
+    /// Work done which has not yet been added to `SEARCH_COUNTERS`.
+    counts: LocalCounts,
+}
+
+impl<'a, G: Adjacency> PathSearch<'a, G> {
+    fn new(
+        graph: &'a G,
+        start_idx: usize,
+        weights: &'a [usize],
+        end_bonus: &'a [usize],
+        upper_bound: usize,
+    ) -> Self {
+        Self::from_root(graph, &[start_idx], weights, end_bonus, upper_bound)
+    }
+
+    /// Creates a search of the subtree of paths which begin with `root`.
+    fn from_root(
+        graph: &'a G,
+        root: &[usize],
+        weights: &'a [usize],
+        end_bonus: &'a [usize],
+        upper_bound: usize,
+    ) -> Self {
+        let max_end_bonus = end_bonus.iter().copied().max().unwrap_or(0);
+        let total_weight = weights.iter().sum::<usize>();
+
+        let mut search = Self {
+            graph,
+            root: root.to_vec(),
+            // This capacity is the longest possible path, so we will never
+            // need to grow these vectors.
+            path: Vec::with_capacity(graph.size()),
+            path_weight: 0,
+            cursors: Vec::with_capacity(graph.size()),
+            visited: BitSet::new(graph.size()),
+            weights,
+            end_bonus,
+            max_end_bonus,
+            total_weight,
+            best: Vec::new(),
+            best_score: 0,
+            upper_bound: upper_bound.min(total_weight + max_end_bonus),
+            incumbent: None,
+            prefix_length: 0,
+            required_end: None,
+            enumeration: None,
+            reached: BitSet::new(graph.size()),
+            queue: Vec::with_capacity(graph.size()),
+            checkpoint_file: None,
+            last_checkpoint: Instant::now(),
+            steps: 0,
+            counts: LocalCounts::default(),
+        };
+
+        for &v in root {
+            search.push(v);
+        }
+        search
+    }
+
+    fn run(&mut self) {
+        while self.step() {}
+        SEARCH_COUNTERS.add(&mut self.counts);
+    }
+
+    /// Tries the next neighbour of the tip of the current path, or backtracks
+    /// if there are none left. Returns false once the search is over.
+    fn step(&mut self) -> bool {
+        // The search is over once it backtracks out of the root path.
+        if self.path.len() < self.root.len() {
+            return false;
+        }
+
+        let tip = *self.path.last().unwrap();
+        if self.best_score >= self.upper_bound || self.score_to_beat() >= self.upper_bound {
+            return false;
+        }
+
+        self.steps += 1;
+        if self.steps.is_multiple_of(4096) {
+            SEARCH_COUNTERS.add(&mut self.counts);
+        }
+        if self.checkpoint_file.is_some()
+            && self.steps.is_multiple_of(4096)
+            && self.last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL
+        {
+            self.save_checkpoint();
+        }
+
+        let depth = self.path.len() - 1;
+        let adjacency_list = self.graph.neighbours(tip);
+
+        // Advance this level's cursor to the next unvisited neighbour.
+        let mut next = None;
+        while self.cursors[depth] < adjacency_list.len() {
+            let candidate = adjacency_list[self.cursors[depth]] as usize;
+            self.cursors[depth] += 1;
+            if !self.visited.contains(candidate) {
+                next = Some(candidate);
+                break;
+            }
+        }
+
+        match next {
+            Some(vertex_index) => {
+                self.counts.nodes += 1;
+                self.push(vertex_index);
+                if !self.can_beat_best() {
+                    self.pop();
+                }
+            }
+            None => self.pop(),
+        }
+
+        true
+    }
+
+    /// Returns the score a path must exceed to be of interest: the best
+    /// found by this search, or by any search sharing the incumbent.
+    fn score_to_beat(&self) -> usize {
+        let incumbent = match self.incumbent {
+            Some(incumbent) => incumbent.get().saturating_sub(self.prefix_length),
+            None => 0,
+        };
+
+        self.best_score.max(incumbent)
+    }
+
+    /// Returns true if extending the current path could possibly produce a
+    /// path scoring more than the best one found so far. The path can be
+    /// extended by at most the unvisited vertices that are still reachable
+    /// from its tip, so if even those are not enough the branch can be cut.
+    fn can_beat_best(&mut self) -> bool {
+        if let Some(enumeration) = &self.enumeration {
+            if self.path.len() >= enumeration.length {
+                return false;
+            }
+        }
+
+        let score_to_beat = self.score_to_beat();
+        let unvisited = self.total_weight - self.path_weight;
+        if self.path_weight + unvisited + self.max_end_bonus <= score_to_beat {
+            self.counts.prune(PruneReason::Size);
+            return false;
+        }
+
+        let (reachable, max_end_bonus) = self.weigh_reachable_unvisited();
+        if self.path_weight + reachable + max_end_bonus <= score_to_beat {
+            self.counts.prune(PruneReason::Reachability);
+            return false;
+        }
+
+        // A longer path has to get to one of the required ends, through
+        // vertices which are not yet on it.
+        let can_reach_end = match &self.required_end {
+            Some(required_end) => required_end
+                .vertices
+                .iter()
+                .any(|&v| self.reached.contains(v as usize) && !self.visited.contains(v as usize)),
+            None => true,
+        };
+
+        if !can_reach_end {
+            self.counts.prune(PruneReason::End);
+        }
+        can_reach_end
+    }
+
+    /// Totals the weight of the unvisited vertices reachable from the tip of
+    /// the current path without passing through any visited vertex. Also
+    /// returns the largest end bonus among those vertices.
+    fn weigh_reachable_unvisited(&mut self) -> (usize, usize) {
+        let tip = match self.path.last() {
+            Some(&tip) => tip,
+            None => return (0, 0),
+        };
+
+        // Treating the visited vertices as already reached means there is
+        // only one bit to test for each neighbour.
+        self.reached.copy_from(&self.visited);
+        self.queue.clear();
+        self.queue.push(tip as u32);
+        let mut head = 0;
+        let mut reachable = 0;
+        let mut max_end_bonus = 0;
+
+        while head < self.queue.len() {
+            let vertex_index = self.queue[head] as usize;
+            head += 1;
+
+            for &adjacency_index in self.graph.neighbours(vertex_index) {
+                let adjacency_index = adjacency_index as usize;
+                if !self.reached.contains(adjacency_index) {
+                    self.reached.insert(adjacency_index);
+                    self.queue.push(adjacency_index as u32);
+                    reachable += self.weights[adjacency_index];
+                    max_end_bonus = max_end_bonus.max(self.end_bonus[adjacency_index]);
+                }
+            }
+        }
+
+        (reachable, max_end_bonus)
+    }
+
+    /// Extends the current path by one vertex, recording it as the best path
+    /// if it now has the highest score seen.
+    fn push(&mut self, vertex_index: usize) {
+        self.path.push(vertex_index);
+        self.path_weight += self.weights[vertex_index];
+        self.cursors.push(0);
+        self.visited.insert(vertex_index);
+
+        if let Some(enumeration) = &mut self.enumeration {
+            if self.path.len() == enumeration.length && self.path[0] < vertex_index {
+                enumeration.found.count += 1;
+                if enumeration.found.paths.len() < enumeration.max_paths {
+                    enumeration.found.paths.push(self.path.clone());
+                }
+            }
+            return;
+        }
+
+        let score = self.path_weight + self.end_bonus[vertex_index];
+        if score > self.best_score && self.can_end_at(vertex_index) {
+            self.best_score = score;
+            self.best.clear();
+            self.best.extend_from_slice(&self.path);
+
+            if let Some(incumbent) = self.incumbent {
+                incumbent.offer(score + self.prefix_length);
+            }
+            SEARCH_COUNTERS.offer_best(score + self.prefix_length);
+        }
+    }
+
+    /// Returns true if the current path, which ends at `vertex_index`, is one
+    /// that the search is looking for.
+    fn can_end_at(&self, vertex_index: usize) -> bool {
+        match &self.required_end {
+            Some(required_end) => {
+                self.path.len() >= required_end.min_length && required_end.contains.contains(vertex_index)
+            }
+            None => true,
+        }
+    }
+
+    /// Writes the current state of the search to the checkpoint file.
+    fn save_checkpoint(&mut self) {
+        let checkpoint_file = match self.checkpoint_file {
+            Some(checkpoint_file) => checkpoint_file,
+            None => return,
+        };
+
+        let checkpoint = Checkpoint {
+            graph_size: self.graph.size(),
+            num_adjacencies: self.num_adjacencies(),
+            start_idx: self.path[0],
+            path: self.path.clone(),
+            cursors: self.cursors.clone(),
+            best: self.best.clone(),
+            best_score: self.best_score,
+        };
+
+        if let Err(e) = checkpoint.save(checkpoint_file) {
+            eprintln!("Unable to write checkpoint {:?}: {}", checkpoint_file, e);
+        }
+
+        self.last_checkpoint = Instant::now();
+    }
+
+    /// Writes a checkpoint which records that the search is complete: every
+    /// vertex on the root path has had all its neighbours tried.
+    fn save_finished_checkpoint(&mut self) {
+        if self.checkpoint_file.is_none() {
+            return;
+        }
+
+        let root = self.root.clone();
+
+        while !self.path.is_empty() {
+            self.pop();
+        }
+        for &v in &root {
+            self.push(v);
+        }
+        for (cursor, &v) in self.cursors.iter_mut().zip(&root) {
+            *cursor = self.graph.neighbours(v).len();
+        }
+
+        self.save_checkpoint();
+    }
+
+    /// Resumes from the checkpoint file, if there is one and it belongs to
+    /// this search. Must be called before the search is run.
+    fn restore_checkpoint(&mut self) {
+        let checkpoint = match self.checkpoint_file.and_then(Checkpoint::load) {
+            Some(checkpoint) => checkpoint,
+            None => return,
+        };
+
+        let start_idx = self.path[0];
+        let is_valid = checkpoint.graph_size == self.graph.size()
+            && checkpoint.num_adjacencies == self.num_adjacencies()
+            && checkpoint.start_idx == start_idx
+            && checkpoint.path.starts_with(&self.root)
+            && self.is_simple_path(&checkpoint.path)
+            && (checkpoint.best.is_empty() || self.is_simple_path(&checkpoint.best))
+            && checkpoint
+                .path
+                .iter()
+                .zip(&checkpoint.cursors)
+                .all(|(&v, &cursor)| cursor <= self.graph.neighbours(v).len());
+
+        if !is_valid {
+            eprintln!(
+                "Ignoring checkpoint {:?} because it does not match the search",
+                self.checkpoint_file.unwrap()
+            );
+            return;
+        }
+
+        while !self.path.is_empty() {
+            self.pop();
+        }
+        for &v in &checkpoint.path {
+            self.push(v);
+        }
+        self.cursors = checkpoint.cursors;
+
+        if let Some(&last) = checkpoint.best.last() {
+            let score = checkpoint.best.iter().map(|&v| self.weights[v]).sum::<usize>()
+                + self.end_bonus[last];
+            // Pushing the path may have recorded a path as good, but the
+            // checkpoint's is the one the search had found.
+            if score >= self.best_score {
+                self.best_score = score;
+                self.best = checkpoint.best;
+
+                if let Some(incumbent) = self.incumbent {
+                    incumbent.offer(score + self.prefix_length);
+                }
+            }
+        }
+    }
+
+    /// Returns true if `path` is a path in the graph which does not visit
+    /// any vertex twice.
+    fn is_simple_path(&self, path: &[usize]) -> bool {
+        let mut seen = vec![false; self.graph.size()];
+
+        for (i, &v) in path.iter().enumerate() {
+            if v >= seen.len() || seen[v] {
+                return false;
+            }
+            if i > 0 && !self.graph.neighbours(path[i - 1]).contains(&(v as u32)) {
+                return false;
+            }
+            seen[v] = true;
+        }
+
+        true
+    }
+
+    /// The total length of the graph's adjacency lists, which is stored in
+    /// checkpoints to help detect when the graph has changed.
+    fn num_adjacencies(&self) -> usize {
+        (0..self.graph.size())
+            .map(|v| self.graph.neighbours(v).len())
+            .sum()
+    }
+
+    /// Backtracks by removing the tip of the current path.
+    fn pop(&mut self) {
+        if let Some(vertex_index) = self.path.pop() {
+            self.path_weight -= self.weights[vertex_index];
+            self.cursors.pop();
+            self.visited.remove(vertex_index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_graphs::{all_paths_from, assert_is_path_from, random_graph};
+    use graph::Graph;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn checkpoint_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("search_{}_{}.checkpoint", std::process::id(), name))
+    }
+
+    /// Returns the search from `start_idx` in `graph` that `longest_path_from`
+    /// would run if the search were not split.
+    fn new_search<'a>(
+        graph: &'a CsrGraph,
+        start_idx: usize,
+        ones: &'a [usize],
+        zeros: &'a [usize],
+    ) -> PathSearch<'a, CsrGraph> {
+        PathSearch::new(graph, start_idx, ones, zeros, graph.size())
+    }
+
+    #[test]
+    fn resumed_search_matches_an_uninterrupted_one() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let filename = checkpoint_file("resumed");
+
+        for _ in 0..100 {
+            let graph = random_graph(&mut rng);
+            let csr = graph.to_csr();
+            let start_idx = rng.gen_range(0..graph.size());
+            let ones = vec![1; graph.size()];
+            let zeros = vec![0; graph.size()];
+
+            let mut uninterrupted = new_search(&csr, start_idx, &ones, &zeros);
+            uninterrupted.run();
+            if uninterrupted.steps == 0 {
+                // The start word on its own met the upper bound.
+                continue;
+            }
+
+            for _ in 0..5 {
+                let steps = rng.gen_range(0..uninterrupted.steps);
+
+                let mut interrupted = new_search(&csr, start_idx, &ones, &zeros);
+                interrupted.checkpoint_file = Some(&filename);
+                for _ in 0..steps {
+                    assert!(interrupted.step());
+                }
+                interrupted.save_checkpoint();
+
+                let mut resumed = new_search(&csr, start_idx, &ones, &zeros);
+                resumed.checkpoint_file = Some(&filename);
+                resumed.restore_checkpoint();
+                assert_eq!(resumed.path, interrupted.path);
+                assert_eq!(resumed.cursors, interrupted.cursors);
+                assert_eq!(resumed.best, interrupted.best);
+
+                resumed.run();
+                assert_eq!(resumed.best_score, uninterrupted.best_score);
+                assert_is_path_from(&graph, &resumed.best, start_idx);
+            }
+        }
+
+        Checkpoint::remove(&filename);
+    }
+
+    #[test]
+    fn checkpoint_of_another_search_is_ignored() {
+        let graph = Graph::from_words(["cold", "cord", "card", "ward", "warm", "word", "worm"]).unwrap();
+        let csr = graph.to_csr();
+        let other_graph = Graph::from_words(["cold", "cord", "card", "ward", "warm"]).unwrap();
+        let other_csr = other_graph.to_csr();
+        let filename = checkpoint_file("other");
+
+        let ones = vec![1; graph.size()];
+        let zeros = vec![0; graph.size()];
+        let cold = graph.get_index_for_word("cold");
+        let expected = longest_path_from(&csr, cold, graph.size(), None, None);
+
+        // A search of another graph, and one from another start word.
+        let other_size = other_graph.size();
+        let mut other_search = new_search(&other_csr, cold, &ones[..other_size], &zeros[..other_size]);
+        let mut other_start = new_search(&csr, graph.get_index_for_word("worm"), &ones, &zeros);
+        for other in [&mut other_search, &mut other_start] {
+            other.checkpoint_file = Some(&filename);
+            other.step();
+            other.step();
+            other.save_checkpoint();
+
+            let mut search = new_search(&csr, cold, &ones, &zeros);
+            search.checkpoint_file = Some(&filename);
+            search.restore_checkpoint();
+            assert_eq!(search.path, [cold]);
+
+            search.run();
+            assert_eq!(search.best.len(), expected.len());
+        }
+
+        Checkpoint::remove(&filename);
+    }
+
+    #[test]
+    fn split_search_matches_an_unsplit_one() {
+        let mut rng = StdRng::seed_from_u64(16);
+
+        for _ in 0..100 {
+            let graph = random_graph(&mut rng);
+            let csr = graph.to_csr();
+            let ones = vec![1; graph.size()];
+            let zeros = vec![0; graph.size()];
+
+            for start_idx in 0..graph.size() {
+                let mut unsplit = new_search(&csr, start_idx, &ones, &zeros);
+                unsplit.run();
+
+                let path = longest_path_from(&csr, start_idx, graph.size(), None, None);
+                assert_is_path_from(&graph, &path, start_idx);
+                assert_eq!(path.len(), unsplit.best.len());
+            }
+        }
+    }
+
+    #[test]
+    fn split_search_from_a_word_with_more_neighbours_than_subtrees() {
+        // A hub with 80 spokes, each joined to the next in runs of 4, and a
+        // tail of 10 words hanging off the last spoke.
+        let mut builder = graph::GraphBuilder::new();
+        for i in 0..80 {
+            builder.add_edge("hub", &format!("s{:02}", i)).unwrap();
+            if i % 4 != 3 {
+                builder.add_edge(&format!("s{:02}", i), &format!("s{:02}", i + 1)).unwrap();
+            }
+        }
+        builder.add_edge("s79", "t00").unwrap();
+        for i in 1..10 {
+            builder.add_edge(&format!("t{:02}", i - 1), &format!("t{:02}", i)).unwrap();
+        }
+        let graph = builder.build();
+        let csr = graph.to_csr();
+        let ones = vec![1; graph.size()];
+        let zeros = vec![0; graph.size()];
+
+        let hub = graph.get_index_for_word("hub");
+        assert!(graph.degree(hub) > SUBTREES);
+        assert_eq!(split_into_subtrees(&csr, hub).len(), graph.degree(hub));
+
+        for start_idx in [hub, graph.get_index_for_word("s00")] {
+            let mut unsplit = new_search(&csr, start_idx, &ones, &zeros);
+            unsplit.run();
+
+            let path = longest_path_from(&csr, start_idx, graph.size(), None, None);
+            assert_is_path_from(&graph, &path, start_idx);
+            assert_eq!(path.len(), unsplit.best.len());
+        }
+
+        // The hub, the last run of spokes and the tail.
+        let path = longest_path_from(&csr, hub, graph.size(), None, None);
+        assert_eq!(path.len(), 1 + 4 + 10);
+    }
+
+    #[test]
+    fn paths_of_length_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(15);
+
+        for _ in 0..40 {
+            let graph = random_graph(&mut rng);
+            let csr = graph.to_csr();
+
+            for start_idx in 0..graph.size() {
+                let paths = all_paths_from(&graph, start_idx);
+
+                for length in 2..=graph.size() {
+                    let expected = paths
+                        .iter()
+                        .filter(|path| path.len() == length && *path.last().unwrap() > start_idx)
+                        .count();
+
+                    let found = paths_of_length_from(&csr, start_idx, length, 3);
+                    assert_eq!(found.count, expected as u64);
+                    assert_eq!(found.paths.len(), expected.min(3));
+                    for path in &found.paths {
+                        assert_is_path_from(&graph, path, start_idx);
+                        assert_eq!(path.len(), length);
+                        assert!(*path.last().unwrap() > start_idx);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use graph::RelativeDirectories;
+
+use crate::chain_file::{is_chain_file, read_chain_file, write_chain_file, ChainFile, Proof, Provenance};
+
+/// The longest chain found for a word length.
+#[derive(Debug)]
+pub struct ChainSummary {
+    pub word_length: usize,
+    pub longest_chain: Vec<String>,
+    /// How `longest_chain` was found, if its file records it.
+    pub longest_chain_provenance: Option<Provenance>,
+    /// The number of start words whose chain is as long as `longest_chain`.
+    pub num_start_words: usize,
+    /// The best upper bound on the length of the longest chain recorded in
+    /// any of the chain files.
+    pub upper_bound: Option<usize>,
+    /// Why no chain can be longer than `longest_chain`, if that is known. The
+    /// reason may be recorded in the file of any chain of the same length.
+    pub proof: Option<Proof>,
+}
+
+impl ChainSummary {
+    pub fn start_word(&self) -> &str {
+        &self.longest_chain[0]
+    }
+
+    pub fn end_word(&self) -> &str {
+        &self.longest_chain[self.longest_chain.len() - 1]
+    }
+
+    /// Returns how many words longer than `longest_chain` the longest chain
+    /// could still be, if there is an upper bound.
+    pub fn gap(&self) -> Option<usize> {
+        self.upper_bound.map(|u| u.saturating_sub(self.longest_chain.len()))
+    }
+
+    /// Returns true if no chain can be longer than `longest_chain`.
+    pub fn is_proven_optimal(&self) -> bool {
+        self.proof.is_some()
+    }
+}
+
+/// Scans the chain files for a word length and returns the longest chain
+/// among them, or None if there are no chain files.
+pub fn summarise_chains(dirs: &RelativeDirectories, word_length: usize) -> Option<ChainSummary> {
+    let dir = dirs.chains_directory(word_length).read_dir().ok()?;
+
+    let mut summary: Option<ChainSummary> = None;
+
+    for file in dir.flatten() {
+        let filename = file.path();
+        if !is_chain_file(dirs, word_length, &filename) {
+            continue;
+        }
+
+        let chain_file = match read_chain_file(&filename) {
+            Ok(chain_file) if !chain_file.chain.is_empty() => chain_file,
+            _ => continue,
+        };
+
+        let upper_bound = chain_file.provenance.map(|p| p.upper_bound);
+        let proof = chain_file.provenance.and_then(|p| p.proof);
+        let chain_length = chain_file.chain.len();
+
+        match &mut summary {
+            Some(s) if chain_length <= s.longest_chain.len() => {
+                if chain_length == s.longest_chain.len() {
+                    s.num_start_words += 1;
+                    s.proof = s.proof.or(proof);
+                }
+                s.upper_bound = min_bound(s.upper_bound, upper_bound);
+            }
+            _ => {
+                let upper_bound = min_bound(summary.as_ref().and_then(|s| s.upper_bound), upper_bound);
+                let ChainFile { chain, provenance } = chain_file;
+                summary = Some(ChainSummary {
+                    word_length,
+                    longest_chain: chain,
+                    longest_chain_provenance: provenance,
+                    num_start_words: 1,
+                    upper_bound,
+                    proof,
+                })
+            }
+        }
+    }
+
+    // The bounds in the other files may be lower than the one in the file of
+    // the longest chain.
+    if let Some(s) = &mut summary {
+        if s.proof.is_none() && s.gap() == Some(0) {
+            s.proof = Some(Proof::UpperBound);
+        }
+    }
+
+    summary
+}
+
+/// Returns the smaller of two optional upper bounds.
+fn min_bound(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        _ => a.or(b),
+    }
+}
+
+/// Writes the output file 'output\chainsNN\00_longest_path.txt'.
+pub fn write_longest_path_file(dirs: &RelativeDirectories, summary: &ChainSummary) {
+    let filename = dirs.longest_path_file(summary.word_length);
+    let chain_file = ChainFile {
+        chain: summary.longest_chain.clone(),
+        provenance: summary.longest_chain_provenance.map(|p| Provenance {
+            upper_bound: summary.upper_bound.unwrap_or(p.upper_bound),
+            proof: summary.proof,
+            ..p
+        }),
+    };
+    write_chain_file(&filename, &chain_file).expect("Unable to write longest path file");
+
+    println!(
+        "The longest chain of word length {} has {} words, from {} to {} ({} start words reach this length)",
+        summary.word_length,
+        summary.longest_chain.len(),
+        summary.start_word(),
+        summary.end_word(),
+        summary.num_start_words
+    );
+
+    match (summary.upper_bound, summary.gap()) {
+        _ if summary.is_proven_optimal() => println!("This is proven to be the longest chain"),
+        (Some(upper_bound), Some(gap)) => println!(
+            "The longest chain has at most {} words, so it could be up to {} words longer",
+            upper_bound, gap
+        ),
+        _ => println!("There is no upper bound on the length of the longest chain"),
+    }
+}
+
+/// Writes the output file 'output\longest_chains.csv', which lists the longest
+/// chain for every word length.
+pub fn write_longest_chains_file(dirs: &RelativeDirectories, summaries: &[ChainSummary]) {
+    let filename = dirs.longest_chains_file();
+    let mut writer = csv::Writer::from_path(&filename).unwrap();
+
+    writer
+        .write_record([
+            "Len",
+            "LongestChainLength",
+            "StartWord",
+            "EndWord",
+            "NumStartWords",
+            "UpperBound",
+            "Gap",
+            "ProvenOptimal",
+            "Proof",
+        ])
+        .unwrap();
+
+    for summary in summaries {
+        writer
+            .serialize((
+                summary.word_length,
+                summary.longest_chain.len(),
+                summary.start_word(),
+                summary.end_word(),
+                summary.num_start_words,
+                summary.upper_bound,
+                summary.gap(),
+                summary.is_proven_optimal(),
+                summary.proof.map(|proof| proof.to_string()),
+            ))
+            .unwrap();
+    }
+
+    println!("Wrote summary of longest chains to {:?}", filename);
+}
+
+/// The longest loop found for a word length.
+#[derive(Debug)]
+pub struct LoopSummary {
+    pub word_length: usize,
+    pub longest_loop: Vec<String>,
+    /// The best upper bound on the length of the longest loop, if known.
+    pub upper_bound: Option<usize>,
+    /// Why no loop can be longer than `longest_loop`, if that is known.
+    pub proof: Option<Proof>,
+}
+
+impl LoopSummary {
+    /// Returns how many words longer than `longest_loop` the longest loop
+    /// could still be, if there is an upper bound.
+    pub fn gap(&self) -> Option<usize> {
+        self.upper_bound.map(|u| u.saturating_sub(self.longest_loop.len()))
+    }
+
+    /// Returns true if no loop can be longer than `longest_loop`.
+    pub fn is_proven_optimal(&self) -> bool {
+        self.proof.is_some()
+    }
+}
+
+/// Reads the longest loop file for a word length, or returns None if there
+/// is no loop.
+pub fn summarise_loops(dirs: &RelativeDirectories, word_length: usize) -> Option<LoopSummary> {
+    let chain_file = read_chain_file(&dirs.longest_loop_file(word_length))
+        .ok()
+        .filter(|chain_file| !chain_file.chain.is_empty())?;
+
+    Some(LoopSummary {
+        word_length,
+        longest_loop: chain_file.chain,
+        upper_bound: chain_file.provenance.map(|p| p.upper_bound),
+        proof: chain_file.provenance.and_then(|p| p.proof),
+    })
+}
+
+/// Writes the output file 'output\longest_loops.csv', which lists the longest
+/// loop for every word length.
+pub fn write_longest_loops_file(dirs: &RelativeDirectories, summaries: &[LoopSummary]) {
+    let filename = dirs.longest_loops_file();
+    let mut writer = csv::Writer::from_path(&filename).unwrap();
+
+    writer
+        .write_record([
+            "Len",
+            "LongestLoopLength",
+            "FirstWord",
+            "UpperBound",
+            "Gap",
+            "ProvenOptimal",
+            "Proof",
+        ])
+        .unwrap();
+
+    for summary in summaries {
+        writer
+            .serialize((
+                summary.word_length,
+                summary.longest_loop.len(),
+                &summary.longest_loop[0],
+                summary.upper_bound,
+                summary.gap(),
+                summary.is_proven_optimal(),
+                summary.proof.map(|proof| proof.to_string()),
+            ))
+            .unwrap();
+    }
+
+    println!("Wrote summary of longest loops to {:?}", filename);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completed_words::create_chain_directories;
+    use std::fs;
+
+    fn write_chain(dirs: &RelativeDirectories, name: &str, chain: &[&str], upper_bound: Option<usize>) {
+        let chain_file = ChainFile {
+            chain: chain.iter().map(|w| w.to_string()).collect(),
+            provenance: upper_bound.map(|u| Provenance::new(true, chain.len(), u)),
+        };
+        write_chain_file(&dirs.chains_directory(4).join(name), &chain_file).unwrap();
+    }
+
+    #[test]
+    fn summary_combines_the_chain_files() {
+        let base = std::env::temp_dir().join(format!("summary_{}", std::process::id()));
+        let dirs = RelativeDirectories::new(base.join("dictionaries"));
+        create_chain_directories(&dirs, &[4]);
+
+        // The lowest bound is in the file of a shorter chain, and the longest
+        // path file is not a chain file.
+        write_chain(&dirs, "cold.txt", &["cold", "cord", "card", "ward", "warm"], Some(9));
+        write_chain(&dirs, "warm.txt", &["warm", "ward", "card", "cord", "cold"], Some(7));
+        write_chain(&dirs, "word.txt", &["word", "cord"], Some(6));
+        write_chain(&dirs, "card.txt", &["card", "cord"], None);
+        write_chain(&dirs, "00_longest_path.txt", &["worm", "word", "cord", "card", "ward", "warm"], Some(6));
+
+        let summary = summarise_chains(&dirs, 4).unwrap();
+        assert_eq!(summary.longest_chain.len(), 5);
+        assert_eq!(summary.num_start_words, 2);
+        assert_eq!(summary.upper_bound, Some(6));
+        assert_eq!(summary.gap(), Some(1));
+        assert_eq!(summary.proof, None);
+
+        // A bound as low as the longest chain proves it.
+        write_chain(&dirs, "ward.txt", &["ward", "card"], Some(5));
+        let summary = summarise_chains(&dirs, 4).unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(summary.upper_bound, Some(5));
+        assert_eq!(summary.gap(), Some(0));
+        assert_eq!(summary.proof, Some(Proof::UpperBound));
+        assert!(summary.is_proven_optimal());
+    }
+
+    #[test]
+    fn min_bound_ignores_missing_bounds() {
+        assert_eq!(min_bound(Some(7), Some(5)), Some(5));
+        assert_eq!(min_bound(None, Some(5)), Some(5));
+        assert_eq!(min_bound(Some(7), None), Some(7));
+        assert_eq!(min_bound(None, None), None);
+    }
+}
@@ -0,0 +1,44 @@
+use graph::Graph;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+
+/// Returns a random graph of up to 16 three-letter words from a
+/// three-letter alphabet, which gives a mix of cycles, bridges, cut
+/// vertices and separate components.
+pub fn random_graph(rng: &mut StdRng) -> Graph {
+    let mut words: Vec<String> = (0..27)
+        .map(|n| [n / 9, n / 3 % 3, n % 3].iter().map(|&c| (b'a' + c) as char).collect())
+        .collect();
+    words.shuffle(rng);
+    let size = rng.gen_range(1..=16);
+    Graph::from_words(&words[..size]).unwrap()
+}
+
+pub fn assert_is_path_from(graph: &Graph, path: &[usize], start_idx: usize) {
+    assert_eq!(path.first(), Some(&start_idx));
+    for pair in path.windows(2) {
+        assert!(graph.is_adjacent(pair[0], pair[1]));
+    }
+    let mut visited = path.to_vec();
+    visited.sort_unstable();
+    visited.dedup();
+    assert_eq!(visited.len(), path.len(), "The path visits a vertex twice");
+}
+
+/// Returns every simple path which starts at `start_idx`, including the path
+/// of just `start_idx`, by trying every way of extending each one.
+pub fn all_paths_from(graph: &Graph, start_idx: usize) -> Vec<Vec<usize>> {
+    let mut paths = Vec::new();
+    let mut unfinished = vec![vec![start_idx]];
+    while let Some(path) = unfinished.pop() {
+        let last = *path.last().unwrap();
+        for a in graph.neighbours(last) {
+            if !path.contains(&a) {
+                let mut longer = path.clone();
+                longer.push(a);
+                unfinished.push(longer);
+            }
+        }
+        paths.push(path);
+    }
+    paths
+}
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use graph::one_letter_different;
+
+use crate::chain_file::{read_chain_file, ChainFileError};
+
+/// Checks the chain files in `paths`, where a directory stands for every
+/// chain file in it, against the words in `corpus`. The errors in each file
+/// are printed, and the result is true if there were none.
+pub fn validate_chain_files(corpus: &HashSet<String>, paths: &[PathBuf]) -> bool {
+    let mut num_files = 0;
+    let mut num_invalid = 0;
+
+    for filename in expand_directories(paths) {
+        num_files += 1;
+
+        let errors = validate_chain_file(corpus, &filename);
+        if !errors.is_empty() {
+            num_invalid += 1;
+            println!("{:?} is invalid:", filename);
+            for error in errors {
+                println!("    {}", error);
+            }
+        }
+    }
+
+    println!("Checked {} chain files, of which {} have errors", num_files, num_invalid);
+    num_invalid == 0
+}
+
+/// Reads the words in a file of words, one per line, such as 'corpus.txt'.
+pub fn read_corpus(filename: &Path) -> io::Result<HashSet<String>> {
+    Ok(fs::read_to_string(filename)?
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|word| !word.is_empty())
+        .collect())
+}
+
+/// Replaces each directory in `paths` with the chain files in it, sorted by name.
+fn expand_directories(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut filenames = Vec::new();
+
+    for path in paths {
+        match path.read_dir() {
+            Ok(dir) => {
+                let mut chain_files: Vec<PathBuf> = dir
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|filename| filename.extension().is_some_and(|ext| ext == "txt"))
+                    .collect();
+                chain_files.sort();
+                filenames.extend(chain_files);
+            }
+            Err(_) => filenames.push(path.clone()),
+        }
+    }
+
+    filenames
+}
+
+/// Checks a chain file, returning a description of each problem found. The
+/// words must all be in `corpus`, be the same length, and not repeat, and
+/// each must be one letter different from the next. Files without a header,
+/// as written by older versions of the program, are allowed, but a header
+/// which does not match the words is an error.
+fn validate_chain_file(corpus: &HashSet<String>, filename: &Path) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    let chain = match read_chain_file(filename) {
+        Ok(chain_file) => chain_file.chain,
+        Err(ChainFileError::Io(e)) => return vec![format!("Unable to read the file: {}", e)],
+        Err(e) => {
+            if !matches!(e, ChainFileError::MissingHeader) {
+                errors.push(format!("The file is damaged: {}", e));
+            }
+
+            // Check whatever words there are anyway.
+            match fs::read_to_string(filename) {
+                Ok(contents) => contents
+                    .lines()
+                    .filter(|line| !line.starts_with('#'))
+                    .flat_map(|line| line.split_whitespace())
+                    .map(str::to_string)
+                    .collect(),
+                Err(e) => return vec![format!("Unable to read the file: {}", e)],
+            }
+        }
+    };
+
+    if chain.is_empty() {
+        errors.push("There are no words in the chain".to_string());
+        return errors;
+    }
+
+    let word_length = chain[0].len();
+    let mut seen = HashSet::new();
+
+    for (i, word) in chain.iter().enumerate() {
+        if !corpus.contains(word) {
+            errors.push(format!("Word {} '{}' is not in the corpus", i + 1, word));
+        }
+
+        if word.len() != word_length {
+            errors.push(format!(
+                "Word {} '{}' has {} letters, but the first word has {}",
+                i + 1,
+                word,
+                word.len(),
+                word_length
+            ));
+        } else if i > 0 && chain[i - 1].len() == word_length && !one_letter_different(&chain[i - 1], word) {
+            errors.push(format!(
+                "Word {} '{}' is not one letter different from the word before it, '{}'",
+                i + 1,
+                word,
+                chain[i - 1]
+            ));
+        }
+
+        if !seen.insert(word.as_str()) {
+            errors.push(format!("Word {} '{}' is already in the chain", i + 1, word));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_file::{write_chain_file, ChainFile};
+
+    fn test_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("validate_{}_{}.txt", std::process::id(), name))
+    }
+
+    /// Writes `chain` to a chain file and returns the errors found in it.
+    fn errors_in(name: &str, chain: &[&str]) -> Vec<String> {
+        let corpus = ["cold", "cord", "card", "ward", "warm", "word"].iter().map(|w| w.to_string()).collect();
+        let filename = test_file(name);
+        let chain_file = ChainFile {
+            chain: chain.iter().map(|w| w.to_string()).collect(),
+            provenance: None,
+        };
+        write_chain_file(&filename, &chain_file).unwrap();
+
+        let errors = validate_chain_file(&corpus, &filename);
+        fs::remove_file(&filename).unwrap();
+        errors
+    }
+
+    #[test]
+    fn valid_chain_is_accepted() {
+        assert!(errors_in("valid", &["cold", "cord", "card", "ward", "warm"]).is_empty());
+    }
+
+    #[test]
+    fn invalid_chains_are_rejected() {
+        let errors = errors_in("not_adjacent", &["cold", "cord", "ward", "warm"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("'ward' is not one letter different"), "{}", errors[0]);
+
+        let errors = errors_in("repeated", &["cold", "cord", "word", "cord"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("'cord' is already in the chain"), "{}", errors[0]);
+
+        let errors = errors_in("unknown", &["cold", "cord", "lord"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("'lord' is not in the corpus"), "{}", errors[0]);
+    }
+}
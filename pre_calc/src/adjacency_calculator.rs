@@ -1,4 +1,4 @@
-use graph::RelativeDirectories;
+use graph::{one_letter_different, RelativeDirectories};
 use rayon::prelude::*;
 use std::io::Write;
 use std::{
@@ -72,22 +72,6 @@ fn calc_adjacency_lists(words: &[String]) -> Vec<WordAdjacencyList> {
         .collect()
 }
 
-fn one_letter_different(w1: &str, w2: &str) -> bool {
-    assert_eq!(w1.len(), w2.len());
-
-    let mut num_diffs = 0;
-    for (a, b) in w1.chars().zip(w2.chars()) {
-        if a != b {
-            num_diffs += 1;
-        }
-        if num_diffs == 2 {
-            return false;
-        }
-    }
-
-    num_diffs == 1
-}
-
 /// Writes one adjacency list file for a particular word length.
 fn write_adjacency_list_file(dirs: &RelativeDirectories, adjacency_lists: &[WordAdjacencyList]) {
     if adjacency_lists.is_empty()
@@ -241,7 +225,7 @@ impl IndexMut<usize> for Corpus {
 impl AddAssign<String> for Corpus {
     /// Adds a new word to the corpus in the appropriate slot.
     fn add_assign(&mut self, word: String) {
-        let word_vec = self.words.entry(word.len()).or_insert_with(|| Vec::new());
+        let word_vec = self.words.entry(word.len()).or_default();
         word_vec.push(word);
     }
 }
@@ -1,4 +1,4 @@
-use graph::{calculate_graph_stats, Graph, RelativeDirectories, WordLengthStatistics};
+use graph::{calculate_graph_stats, Graph, GraphError, RelativeDirectories, WordLengthStatistics};
 use rayon::prelude::*;
 use std::io::Write;
 use std::path::Path;
@@ -23,21 +23,28 @@ pub(crate) fn calculate_initial_graphs(dirs: &RelativeDirectories) {
         .filter_map(|word_length| {
             let filename = dirs.all_adjacency_file(word_length);
 
-            Graph::load_from_adjacency_file(&filename)
-                .map(|graph| {
+            match Graph::load_from_adjacency_file(&filename) {
+                Ok(graph) => {
                     println!(
                         "Loaded graph for word length of {} from {:?}",
                         word_length, filename
                     );
 
+                    write_graph_file(&dirs.all_graph_file(word_length), &graph);
                     let stats = calculate_graph_stats(&graph);
-                    (graph, stats)
-                })
-                .ok()
+                    Some((graph, stats))
+                }
+                // Not every word length has words.
+                Err(GraphError::Io { error, .. }) if error.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    eprintln!("Unable to load the graph of word length {}: {}", word_length, e);
+                    None
+                }
+            }
         })
         .unzip();
 
-    graphs.sort_unstable_by(|a, b| a.word_length().cmp(&b.word_length()));
+    graphs.sort_unstable_by_key(|a| a.word_length());
     write_word_stats(&dirs.word_stats_file(), &stats);
     write_largest_components_to_file(dirs, &graphs);
 }
@@ -46,7 +53,7 @@ fn write_word_stats(stats_file: &Path, stats: &[WordLengthStatistics]) {
     let mut writer = csv::Writer::from_path(stats_file).unwrap();
 
     writer
-        .write_record(&[
+        .write_record([
             "Len",
             "WordCount",
             "ComponentCount",
@@ -103,7 +110,7 @@ fn write_largest_components_to_file(dirs: &RelativeDirectories, graphs: &[Graph]
     for graph in graphs {
         let components = graph.components();
         let comp = components
-            .get(0)
+            .first()
             .expect("At least one component should exist");
 
         let filename = dirs.largest_component_adjacency_file(graph.word_length());
@@ -111,15 +118,26 @@ fn write_largest_components_to_file(dirs: &RelativeDirectories, graphs: &[Graph]
         let rw_file = fs::File::create(filename).unwrap();
         let mut writer = io::BufWriter::new(rw_file);
 
-        for v in graph.vertices.iter().filter(|v| v.component == comp.number) {
-            write!(writer, "{}", v.word).unwrap();
+        let component_indices: Vec<usize> = graph.component_vertices(comp.number).collect();
+
+        for &idx in &component_indices {
+            write!(writer, "{}", graph.word(idx)).unwrap();
 
-            for word_index in &v.adjacency_list {
-                let v2 = &graph.vertices[*word_index];
-                write!(writer, " {}", v2.word).unwrap();
+            for neighbour in graph.neighbours(idx) {
+                write!(writer, " {}", graph.word(neighbour)).unwrap();
             }
 
             writeln!(writer).unwrap();
         }
+
+        let component_graph = graph.subgraph(&component_indices);
+        write_graph_file(&dirs.largest_component_graph_file(graph.word_length()), &component_graph);
     }
 }
+
+/// Writes a graph in the binary format, which longest_path_calculator loads
+/// in preference to the adjacency files.
+fn write_graph_file(filename: &Path, graph: &Graph) {
+    println!("Writing {:?}", filename);
+    graph.write_binary_file(filename).unwrap();
+}